@@ -0,0 +1,75 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // owner method to set the minimum payout, smaller amounts are carried over to a later distribution
+    pub fn set_min_payout(&mut self, min_payout: U128) {
+        self.assert_owner();
+        self.min_payout = min_payout.0;
+    }
+
+    pub fn get_min_payout(&self) -> U128 {
+        U128(self.min_payout)
+    }
+
+    // keeper method to credit the carried over balance of the given receiver regardless of the minimum payout,
+    // e.g. once it is no longer a reward receiver
+    pub fn flush_carry_over(&mut self, account_id: AccountId) {
+        self.assert_role(Role::Keeper);
+        let amount = self.carry_over.remove(&account_id).expect("Nothing carried over");
+        self.total_carry_over -= amount;
+        self.internal_credit(&account_id, amount);
+        self.internal_update_receiver_stats(&account_id, amount);
+        Event::CarryOverFlushed {
+            account_id: account_id.clone(),
+            amount: U128(amount),
+        }.emit();
+        self.internal_record_distribution(
+            None,
+            DistributionSource::CarryOver,
+            amount,
+            vec![(account_id, U128(amount))],
+            env::predecessor_account_id(),
+            0,
+        );
+    }
+
+    pub fn get_carry_over(&self, account_id: AccountId) -> U128 {
+        U128(self.carry_over.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn get_total_carry_over(&self) -> U128 {
+        U128(self.total_carry_over)
+    }
+}
+
+impl Contract {
+    // adds the carried over balance of the receiver to its payout,
+    // returns the amount to pay now and the amount carried over
+    pub(crate) fn internal_batch_payout(&self, account_id: &AccountId, amount: Balance) -> (Balance, Balance) {
+        let amount = amount + self.carry_over.get(account_id).unwrap_or(0);
+        if amount < self.min_payout {
+            (0, amount)
+        } else {
+            (amount, 0)
+        }
+    }
+
+    // returns the amount to pay now, the rest is carried over to a later distribution
+    pub(crate) fn internal_carry_over(&mut self, staking_pool_account_id: &AccountId, account_id: &AccountId, amount: Balance) -> Balance {
+        let (payout, carried_over) = self.internal_batch_payout(account_id, amount);
+        let previous = self.carry_over.get(account_id).unwrap_or(0);
+        self.total_carry_over = self.total_carry_over - previous + carried_over;
+        if carried_over > 0 {
+            self.carry_over.insert(account_id, &carried_over);
+            Event::RewardCarriedOver {
+                staking_pool_account_id: staking_pool_account_id.clone(),
+                account_id: account_id.clone(),
+                amount: U128(carried_over),
+            }.emit();
+        } else if previous > 0 {
+            self.carry_over.remove(account_id);
+        }
+        payout
+    }
+}
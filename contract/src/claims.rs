@@ -0,0 +1,58 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // public method to transfer the pending rewards of the caller
+    pub fn claim(&mut self) -> Promise {
+        self.internal_claim(env::predecessor_account_id())
+    }
+
+    // keeper method to push the pending rewards to the given receiver
+    pub fn claim_for(&mut self, account_id: AccountId) -> Promise {
+        self.assert_role(Role::Keeper);
+        self.internal_claim(account_id)
+    }
+
+    #[private]
+    pub fn on_claim(&mut self, account_id: AccountId, amount: U128) {
+        self.transfers_in_flight -= amount.0;
+        if is_promise_success() {
+            Event::RewardClaimed { account_id, amount }.emit();
+        } else {
+            self.internal_credit(&account_id, amount.0);
+            Event::RewardClaimFailed { account_id, amount }.emit();
+        }
+    }
+
+    pub fn get_pending_rewards(&self, account_id: AccountId) -> U128 {
+        U128(self.pending_rewards.get(&account_id).unwrap_or(0))
+    }
+
+    pub fn get_total_pending_rewards(&self) -> U128 {
+        U128(self.total_pending_rewards)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_credit(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount > 0 {
+            let pending_rewards = self.pending_rewards.get(account_id).unwrap_or(0);
+            self.pending_rewards.insert(account_id, &(pending_rewards + amount));
+            self.total_pending_rewards += amount;
+        }
+    }
+
+    fn internal_claim(&mut self, account_id: AccountId) -> Promise {
+        let amount = self.pending_rewards.remove(&account_id).unwrap_or(0);
+        assert!(amount > 0, "Nothing to claim");
+        self.total_pending_rewards -= amount;
+        self.transfers_in_flight += amount;
+
+        Promise::new(account_id.clone())
+            .transfer(amount)
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(ON_CLAIM_GAS)
+            .on_claim(account_id, U128(amount))
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Donation {
+    pub account_id: AccountId,
+    #[serde(with = "u128_dec_format")]
+    pub amount: Balance,
+    #[serde(with = "u64_dec_format")]
+    pub timestamp: Timestamp,
+}
+
+const DEFAULT_DONATIONS_LIMIT: u64 = 50;
+
+#[near_bindgen]
+impl Contract {
+    // public method to donate the attached deposit, it is shared out with `distribute_free_balance`,
+    // the deposit has to cover at least the storage of the donation record
+    #[payable]
+    pub fn donate(&mut self) {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach the donation");
+        let account_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+        self.donations.push(&Donation {
+            account_id: account_id.clone(),
+            amount,
+            timestamp: env::block_timestamp(),
+        });
+        let storage_cost = Balance::from(env::storage_usage() - initial_storage_usage) * env::storage_byte_cost();
+        assert!(amount >= storage_cost, "The donation must cover its storage cost of {}", storage_cost);
+        Event::DonationReceived { account_id, amount: U128(amount) }.emit();
+    }
+
+    // keeper method to split the contract balance above the storage cost, the storage reserve and the credited
+    // rewards among the reward receivers of the given staking pool
+    pub fn distribute_free_balance(&mut self, staking_pool_account_id: AccountId) -> U128 {
+        self.assert_role(Role::Keeper);
+        // rewards withdrawn by an in flight distribution are already in the balance but not yet credited
+        assert!(
+            self.staking_pools.values().all(|staking_pool| !staking_pool.distribution_locked),
+            "A distribution is in progress"
+        );
+        let amount = self.internal_free_balance();
+        assert!(amount > 0, "No free balance to distribute");
+
+        self.internal_apply_pending_reward_receivers(&staking_pool_account_id);
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        assert!(staking_pool.is_active(), "The staking pool is not active until epoch {}", staking_pool.activation_epoch);
+        self.internal_distribute(
+            &staking_pool_account_id,
+            &mut staking_pool,
+            DistributionSource::Donation,
+            amount,
+            env::predecessor_account_id(),
+            0,
+        );
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+        U128(amount)
+    }
+
+    // owner method to set the balance kept on top of the storage cost for future storage
+    pub fn set_storage_reserve(&mut self, storage_reserve: U128) {
+        self.assert_owner();
+        self.storage_reserve = storage_reserve.0;
+    }
+
+    pub fn get_storage_reserve(&self) -> U128 {
+        U128(self.storage_reserve)
+    }
+
+    pub fn get_free_balance(&self) -> U128 {
+        U128(self.internal_free_balance())
+    }
+
+    pub fn get_donations_count(&self) -> u64 {
+        self.donations.len()
+    }
+
+    // donations, oldest first, paired with their ids
+    pub fn get_donations(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<(u64, Donation)> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_DONATIONS_LIMIT);
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.donations.len()))
+            .map(|id| (id, self.donations.get(id).unwrap()))
+            .collect()
+    }
+}
+
+impl Contract {
+    // transfers in flight are held back until their callbacks ran, a refund may arrive before them
+    fn internal_free_balance(&self) -> Balance {
+        let storage_cost = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        env::account_balance().saturating_sub(
+            storage_cost + self.storage_reserve + self.total_pending_rewards + self.total_carry_over + self.transfers_in_flight
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    fn account(account_id: &str) -> AccountId {
+        account_id.parse().unwrap()
+    }
+
+    fn donate(contract: &mut Contract, attached_deposit: Balance) {
+        testing_env!(VMContextBuilder::new()
+            .predecessor_account_id(account("donor.near"))
+            .attached_deposit(attached_deposit)
+            .build());
+        contract.donate();
+    }
+
+    fn contract() -> Contract {
+        testing_env!(VMContextBuilder::new().build());
+        Contract::new(
+            account("pool.near"),
+            account("owner.near"),
+            vec![(account("receiver.near"), RewardShare::Fraction(RewardFeeFraction { numerator: 1, denominator: 1 }))],
+        )
+    }
+
+    #[test]
+    #[should_panic(expected = "The donation must cover its storage cost")]
+    fn donation_below_storage_cost_is_rejected() {
+        let mut contract = contract();
+        donate(&mut contract, 1);
+    }
+
+    #[test]
+    fn donation_covering_storage_cost_is_recorded() {
+        let mut contract = contract();
+        donate(&mut contract, 10u128.pow(22));
+        assert_eq!(contract.get_donations_count(), 1);
+    }
+}
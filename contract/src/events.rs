@@ -0,0 +1,164 @@
+use crate::*;
+
+const EVENT_STANDARD: &str = "staking-pool-owner";
+const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 events emitted as `EVENT_JSON:` logs.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Event {
+    WithdrawStarted {
+        staking_pool_account_id: AccountId,
+    },
+    UnstakeAll {
+        staking_pool_account_id: AccountId,
+    },
+    Unstake {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    UnstakeFailed {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    RetainedStakeReleased {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    RetainedStakeReleaseFailed {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    PrincipalDeposited {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    PrincipalDepositFailed {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    PrincipalUnstaked {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    PrincipalUnstakeFailed {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    WithdrawSucceeded {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    WithdrawFailed {
+        staking_pool_account_id: AccountId,
+        amount: U128,
+    },
+    DistributionFailed {
+        staking_pool_account_id: AccountId,
+        reason: String,
+    },
+    DistributionUnlocked {
+        staking_pool_account_id: AccountId,
+    },
+    RewardPayout {
+        staking_pool_account_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    },
+    RewardCarriedOver {
+        staking_pool_account_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    },
+    CarryOverFlushed {
+        account_id: AccountId,
+        amount: U128,
+    },
+    KeeperBountyProposed {
+        keeper_bounty: KeeperBounty,
+        effective_epoch: EpochHeight,
+    },
+    KeeperBountyPaid {
+        staking_pool_account_id: AccountId,
+        account_id: AccountId,
+        amount: U128,
+    },
+    DonationReceived {
+        account_id: AccountId,
+        amount: U128,
+    },
+    RewardClaimed {
+        account_id: AccountId,
+        amount: U128,
+    },
+    RewardClaimFailed {
+        account_id: AccountId,
+        amount: U128,
+    },
+    RewardReceiversProposed {
+        staking_pool_account_id: AccountId,
+        reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+        effective_epoch: EpochHeight,
+    },
+    RewardReceiversChangeCancelled {
+        staking_pool_account_id: AccountId,
+    },
+    RewardReceiversChanged {
+        staking_pool_account_id: AccountId,
+        reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    },
+    StakingPoolAdded {
+        staking_pool_account_id: AccountId,
+    },
+    StakingPoolRemoved {
+        staking_pool_account_id: AccountId,
+    },
+    StakingPoolOwnerCall {
+        staking_pool_account_id: AccountId,
+        method_name: String,
+        success: bool,
+    },
+    OwnerProposed {
+        owner_id: AccountId,
+        expires_at: U64,
+    },
+    OwnerProposalCancelled {
+        owner_id: AccountId,
+    },
+    OwnerChanged {
+        old_owner_id: AccountId,
+        new_owner_id: AccountId,
+    },
+    RoleGranted {
+        role: Role,
+        account_id: AccountId,
+    },
+    RoleRevoked {
+        role: Role,
+        account_id: AccountId,
+    },
+    IpfsHashChanged {
+        ipfs_hash: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+impl Event {
+    pub fn emit(&self) {
+        let event_log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_STANDARD_VERSION,
+            event: self,
+        };
+        log!("EVENT_JSON:{}", near_sdk::serde_json::to_string(&event_log).unwrap());
+    }
+}
@@ -0,0 +1,324 @@
+use crate::*;
+
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum FractionError {
+    ZeroDenominator,
+    GreaterThanOne,
+    Overflow,
+    InvalidPercentage,
+}
+
+impl fmt::Display for FractionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FractionError::ZeroDenominator => write!(f, "Denominator must be a positive number"),
+            FractionError::GreaterThanOne => write!(f, "The reward fee must be less or equal to 1"),
+            FractionError::Overflow => write!(f, "The reward fee is too precise to be represented"),
+            FractionError::InvalidPercentage => write!(f, "Expected a percentage like \"12.5%\" with at most {} decimals", MAX_PERCENTAGE_DECIMALS),
+        }
+    }
+}
+
+/// Rational number kept in lowest terms, used for all reward share arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ratio {
+    numerator: u128,
+    denominator: u128,
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+impl Ratio {
+    pub fn new(numerator: u128, denominator: u128) -> Result<Self, FractionError> {
+        if denominator == 0 {
+            return Err(FractionError::ZeroDenominator);
+        }
+        let divisor = gcd(numerator, denominator);
+        Ok(Self {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+
+    pub fn zero() -> Self {
+        Self { numerator: 0, denominator: 1 }
+    }
+
+    pub fn one() -> Self {
+        Self { numerator: 1, denominator: 1 }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    pub fn is_one(&self) -> bool {
+        self.numerator == self.denominator
+    }
+
+    pub fn checked_add(self, other: Ratio) -> Result<Self, FractionError> {
+        let divisor = gcd(self.denominator, other.denominator);
+        let left = self.numerator.checked_mul(other.denominator / divisor);
+        let right = other.numerator.checked_mul(self.denominator / divisor);
+        let numerator = left.zip(right).and_then(|(left, right)| left.checked_add(right));
+        let denominator = (self.denominator / divisor).checked_mul(other.denominator);
+        match (numerator, denominator) {
+            (Some(numerator), Some(denominator)) => Ratio::new(numerator, denominator),
+            _ => Err(FractionError::Overflow),
+        }
+    }
+
+    pub fn checked_sub(self, other: Ratio) -> Result<Self, FractionError> {
+        let divisor = gcd(self.denominator, other.denominator);
+        let left = self.numerator.checked_mul(other.denominator / divisor);
+        let right = other.numerator.checked_mul(self.denominator / divisor);
+        let numerator = left.zip(right).and_then(|(left, right)| left.checked_sub(right));
+        let denominator = (self.denominator / divisor).checked_mul(other.denominator);
+        match (numerator, denominator) {
+            (Some(numerator), Some(denominator)) => Ratio::new(numerator, denominator),
+            _ => Err(FractionError::Overflow),
+        }
+    }
+
+    pub fn checked_div(self, other: Ratio) -> Result<Self, FractionError> {
+        self.checked_mul(Ratio::new(other.denominator, other.numerator)?)
+    }
+
+    pub fn checked_mul(self, other: Ratio) -> Result<Self, FractionError> {
+        // cross-reduce first so the products stay as small as possible
+        let left = gcd(self.numerator, other.denominator);
+        let right = gcd(other.numerator, self.denominator);
+        let numerator = (self.numerator / left).checked_mul(other.numerator / right);
+        let denominator = (self.denominator / right).checked_mul(other.denominator / left);
+        match (numerator, denominator) {
+            (Some(numerator), Some(denominator)) => Ratio::new(numerator, denominator),
+            _ => Err(FractionError::Overflow),
+        }
+    }
+
+    pub fn multiply(&self, value: Balance) -> Balance {
+        (U256::from(self.numerator) * U256::from(value) / U256::from(self.denominator)).as_u128()
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardFeeFraction {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl RewardFeeFraction {
+    pub fn checked_ratio(&self) -> Result<Ratio, FractionError> {
+        let ratio = Ratio::new(self.numerator.into(), self.denominator.into())?;
+        if ratio.numerator > ratio.denominator {
+            return Err(FractionError::GreaterThanOne);
+        }
+        Ok(ratio)
+    }
+
+    pub fn assert_valid(&self) {
+        if let Err(err) = self.checked_ratio() {
+            env::panic_str(&err.to_string());
+        }
+    }
+
+    pub fn zero_fee() -> RewardFeeFraction {
+        RewardFeeFraction {
+            numerator: 0,
+            denominator: 1
+        }
+    }
+
+    pub fn multiply(&self, value: Balance) -> Balance {
+        self.checked_ratio().unwrap_or_else(|err| env::panic_str(&err.to_string())).multiply(value)
+    }
+
+    pub fn checked_add(&self, other: &RewardFeeFraction) -> Result<RewardFeeFraction, FractionError> {
+        let sum = self.checked_ratio()?.checked_add(other.checked_ratio()?)?;
+        RewardFeeFraction::try_from(sum)
+    }
+
+    pub fn add(&self, other: RewardFeeFraction) -> RewardFeeFraction {
+        self.checked_add(&other).unwrap_or_else(|err| env::panic_str(&err.to_string()))
+    }
+}
+
+impl TryFrom<Ratio> for RewardFeeFraction {
+    type Error = FractionError;
+
+    fn try_from(ratio: Ratio) -> Result<Self, Self::Error> {
+        match (u32::try_from(ratio.numerator), u32::try_from(ratio.denominator)) {
+            (Ok(numerator), Ok(denominator)) => Ok(RewardFeeFraction { numerator, denominator }),
+            _ => Err(FractionError::Overflow),
+        }
+    }
+}
+
+pub(crate) const BASIS_POINTS_DENOMINATOR: u32 = 10_000;
+const MAX_PERCENTAGE_DECIMALS: usize = 6;
+
+/// Reward share of a receiver as accepted by `new`, `add_staking_pool` and `reset_reward_receivers`:
+/// `{"numerator": 1, "denominator": 8}`, `{"basis_points": 1250}` or `"12.5%"`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde", untagged)]
+pub enum RewardShare {
+    Fraction(RewardFeeFraction),
+    BasisPoints { basis_points: u32 },
+    Percentage(String),
+}
+
+impl RewardShare {
+    pub fn to_fee_fraction(&self) -> Result<RewardFeeFraction, FractionError> {
+        let ratio = match self {
+            RewardShare::Fraction(fraction) => fraction.checked_ratio()?,
+            RewardShare::BasisPoints { basis_points } => {
+                Ratio::new((*basis_points).into(), BASIS_POINTS_DENOMINATOR.into())?
+            }
+            RewardShare::Percentage(percentage) => parse_percentage(percentage)?,
+        };
+        let fraction = RewardFeeFraction::try_from(ratio)?;
+        fraction.checked_ratio()?;
+        Ok(fraction)
+    }
+}
+
+fn parse_percentage(percentage: &str) -> Result<Ratio, FractionError> {
+    let value = percentage.trim().strip_suffix('%').ok_or(FractionError::InvalidPercentage)?.trim_end();
+    let (integer, decimals) = match value.find('.') {
+        Some(index) if index + 1 < value.len() => (&value[..index], &value[index + 1..]),
+        Some(_) => return Err(FractionError::InvalidPercentage),
+        None => (value, ""),
+    };
+    if integer.is_empty()
+        || decimals.len() > MAX_PERCENTAGE_DECIMALS
+        || !integer.chars().chain(decimals.chars()).all(|c| c.is_ascii_digit())
+    {
+        return Err(FractionError::InvalidPercentage);
+    }
+
+    // the decimals are bounded, so only an over-long integer part can fail to parse
+    let numerator: u128 = format!("{}{}", integer, decimals).parse().map_err(|_| FractionError::InvalidPercentage)?;
+    Ratio::new(numerator, 100 * 10u128.pow(decimals.len() as u32))
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(numerator: u32, denominator: u32) -> RewardFeeFraction {
+        RewardFeeFraction { numerator, denominator }
+    }
+
+    fn ratio(numerator: u128, denominator: u128) -> Ratio {
+        Ratio::new(numerator, denominator).unwrap()
+    }
+
+    #[test]
+    fn ratio_is_reduced() {
+        assert_eq!(ratio(250, 1000), ratio(1, 4));
+        assert_eq!(ratio(0, 7), Ratio::zero());
+        assert_eq!(Ratio::new(1, 0), Err(FractionError::ZeroDenominator));
+    }
+
+    #[test]
+    fn checked_ops() {
+        assert_eq!(ratio(1, 3).checked_add(ratio(1, 6)), Ok(ratio(1, 2)));
+        assert_eq!(ratio(1, 2).checked_sub(ratio(1, 3)), Ok(ratio(1, 6)));
+        assert_eq!(ratio(1, 3).checked_sub(ratio(1, 2)), Err(FractionError::Overflow));
+        assert_eq!(ratio(2, 3).checked_mul(ratio(3, 4)), Ok(ratio(1, 2)));
+        assert_eq!(ratio(1, 2).checked_div(ratio(1, 4)), Ok(ratio(2, 1)));
+        assert_eq!(ratio(1, 2).checked_div(Ratio::zero()), Err(FractionError::ZeroDenominator));
+        assert_eq!(ratio(1, u128::MAX).checked_add(ratio(1, u128::MAX - 1)), Err(FractionError::Overflow));
+    }
+
+    #[test]
+    fn five_receivers_with_denominator_1000_sum_up_to_one() {
+        let fractions = [fraction(199, 1000), fraction(201, 1000), fraction(200, 1000), fraction(150, 1000), fraction(250, 1000)];
+        let total = fractions
+            .iter()
+            .try_fold(Ratio::zero(), |total, fraction| total.checked_add(fraction.checked_ratio()?))
+            .unwrap();
+        assert!(total.is_one());
+
+        let reward_receivers: Vec<(AccountId, RewardFeeFraction)> = fractions
+            .iter()
+            .enumerate()
+            .map(|(index, fraction)| (format!("receiver_{}.near", index).parse().unwrap(), fraction.clone()))
+            .collect();
+        assert_reward_receivers(&"pool.near".parse().unwrap(), &reward_receivers);
+    }
+
+    #[test]
+    fn fraction_validation() {
+        assert_eq!(fraction(1, 0).checked_ratio(), Err(FractionError::ZeroDenominator));
+        assert_eq!(fraction(3, 2).checked_ratio(), Err(FractionError::GreaterThanOne));
+        assert!(fraction(2, 2).checked_ratio().unwrap().is_one());
+    }
+
+    #[test]
+    fn multiply_rounds_down_without_overflow() {
+        assert_eq!(fraction(1, 3).multiply(10), 3);
+        assert_eq!(fraction(u32::MAX - 1, u32::MAX).multiply(u128::MAX), u128::MAX - u128::MAX / u128::from(u32::MAX));
+        assert_eq!(RewardFeeFraction::zero_fee().multiply(u128::MAX), 0);
+    }
+
+    fn percentage(value: &str) -> Result<(u32, u32), FractionError> {
+        RewardShare::Percentage(value.to_string())
+            .to_fee_fraction()
+            .map(|fraction| (fraction.numerator, fraction.denominator))
+    }
+
+    #[test]
+    fn percentage_shares() {
+        assert_eq!(percentage("12.5%"), Ok((1, 8)));
+        assert_eq!(percentage("100%"), Ok((1, 1)));
+        assert_eq!(percentage("0.000001%"), Ok((1, 100_000_000)));
+        assert_eq!(percentage(" 33 % "), Ok((33, 100)));
+        assert_eq!(percentage(".5%"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("5.%"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("1000000000000000000000000000000000000000%"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("100.0000001%"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("12.5"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("-5%"), Err(FractionError::InvalidPercentage));
+        assert_eq!(percentage("100.5%"), Err(FractionError::GreaterThanOne));
+    }
+
+    #[test]
+    fn basis_point_shares() {
+        let share = |basis_points| {
+            RewardShare::BasisPoints { basis_points }
+                .to_fee_fraction()
+                .map(|fraction| (fraction.numerator, fraction.denominator))
+        };
+        assert_eq!(share(1250), Ok((1, 8)));
+        assert_eq!(share(10_000), Ok((1, 1)));
+        assert_eq!(share(10_001), Err(FractionError::GreaterThanOne));
+    }
+
+    #[test]
+    fn shares_deserialize_from_every_format() {
+        let shares: Vec<RewardShare> =
+            near_sdk::serde_json::from_str(r#"[{"numerator": 1, "denominator": 8}, {"basis_points": 1250}, "12.5%"]"#).unwrap();
+        for share in shares {
+            let fraction = share.to_fee_fraction().unwrap();
+            assert_eq!((fraction.numerator, fraction.denominator), (1, 8));
+        }
+    }
+}
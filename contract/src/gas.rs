@@ -0,0 +1,93 @@
+use crate::*;
+
+/// Gas attached along the withdraw promise chain. Callback entries only cover the callback itself,
+/// the gas of the promises it issues is added on top.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GasConfig {
+    /// `withdraw` itself, per staking pool.
+    pub withdraw_call: Gas,
+    pub staking_pool_ping: Gas,
+    pub staking_pool_get_account: Gas,
+    pub staking_pool_withdraw: Gas,
+    pub staking_pool_unstake: Gas,
+    pub on_get_account: Gas,
+    pub on_withdraw: Gas,
+    pub on_withdraw_per_receiver: Gas,
+}
+
+impl Default for GasConfig {
+    fn default() -> Self {
+        Self {
+            withdraw_call: Gas(10_000_000_000_000),
+            staking_pool_ping: Gas(50_000_000_000_000),
+            staking_pool_get_account: Gas(5_000_000_000_000),
+            staking_pool_withdraw: Gas(25_000_000_000_000),
+            staking_pool_unstake: Gas(50_000_000_000_000),
+            on_get_account: Gas(10_000_000_000_000),
+            on_withdraw: Gas(10_000_000_000_000),
+            on_withdraw_per_receiver: Gas(3_000_000_000_000),
+        }
+    }
+}
+
+impl GasConfig {
+    pub fn on_withdraw_gas(&self, reward_receivers_count: u64) -> Gas {
+        self.on_withdraw + self.on_withdraw_per_receiver * reward_receivers_count + self.staking_pool_unstake + ON_STAKE_CHANGE_GAS
+    }
+
+    pub fn on_get_account_gas(&self, reward_receivers_count: u64) -> Gas {
+        self.on_get_account + self.staking_pool_withdraw + self.on_withdraw_gas(reward_receivers_count)
+    }
+
+    /// Minimum gas `withdraw` needs to distribute the rewards of one staking pool.
+    pub fn withdraw_gas(&self, reward_receivers_count: u64) -> Gas {
+        self.withdraw_call
+            + self.staking_pool_ping
+            + self.staking_pool_get_account
+            + self.on_get_account_gas(reward_receivers_count)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_gas_config(&mut self, gas_config: GasConfig) {
+        self.assert_owner();
+        assert!(
+            gas_config.withdraw_gas(MAX_REWARD_RECEIVERS as u64) <= MAX_PREPAID_GAS,
+            "The withdraw of a staking pool with {} reward receivers must fit into {} gas",
+            MAX_REWARD_RECEIVERS,
+            MAX_PREPAID_GAS.0
+        );
+        self.gas_config = gas_config;
+    }
+
+    pub fn get_gas_config(&self) -> GasConfig {
+        self.gas_config.clone()
+    }
+
+    // minimum gas to attach to `withdraw` for the given staking pool whether or not it is due yet,
+    // or to distribute every pool that is due at once
+    pub fn get_min_withdraw_gas(&self, staking_pool_account_id: Option<AccountId>) -> Gas {
+        if let Some(staking_pool_account_id) = staking_pool_account_id {
+            self.internal_withdraw_gas(&self.internal_get_staking_pool(&staking_pool_account_id))
+        } else {
+            self.staking_pools
+                .values()
+                .filter(|staking_pool| staking_pool.is_due())
+                .map(|staking_pool| self.internal_withdraw_gas(&staking_pool))
+                .fold(Gas(0), |total, gas| total + gas)
+        }
+    }
+}
+
+impl Contract {
+
+    pub(crate) fn internal_withdraw_gas(&self, staking_pool: &StakingPool) -> Gas {
+        // pending reward receivers may be applied by this withdraw
+        let pending_reward_receivers_count = staking_pool.pending_reward_receivers
+            .as_ref()
+            .map_or(0, |pending| pending.reward_receivers.len() as u64);
+        self.gas_config.withdraw_gas(std::cmp::max(staking_pool.reward_receivers.len(), pending_reward_receivers_count))
+    }
+}
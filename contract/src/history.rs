@@ -0,0 +1,76 @@
+use crate::*;
+
+/// Where the distributed balance came from.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionSource {
+    /// Rewards withdrawn from the staking pool.
+    StakingPool,
+    /// Free contract balance, e.g. donations, split by the receivers of the staking pool.
+    Donation,
+    /// Carried over balance credited by `flush_carry_over`, it belongs to no staking pool.
+    CarryOver,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Distribution {
+    pub staking_pool_account_id: Option<AccountId>,
+    pub source: DistributionSource,
+    pub epoch_height: EpochHeight,
+    #[serde(with = "u64_dec_format")]
+    pub timestamp: Timestamp,
+    #[serde(with = "u128_dec_format")]
+    pub amount: Balance,
+    pub payouts: Vec<(AccountId, U128)>,
+    /// Caller of the `withdraw` that triggered the distribution.
+    pub keeper_id: AccountId,
+    #[serde(with = "u128_dec_format")]
+    pub keeper_bounty: Balance,
+}
+
+const DEFAULT_DISTRIBUTIONS_LIMIT: u64 = 50;
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_distributions_count(&self) -> u64 {
+        self.distributions.len()
+    }
+
+    // completed distributions, oldest first, paired with their ids
+    pub fn get_distributions(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<(u64, Distribution)> {
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(DEFAULT_DISTRIBUTIONS_LIMIT);
+        (from_index..std::cmp::min(from_index.saturating_add(limit), self.distributions.len()))
+            .map(|id| (id, self.distributions.get(id).unwrap()))
+            .collect()
+    }
+
+    pub fn get_distribution(&self, id: u64) -> Option<Distribution> {
+        self.distributions.get(id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_record_distribution(
+        &mut self,
+        staking_pool_account_id: Option<AccountId>,
+        source: DistributionSource,
+        amount: Balance,
+        payouts: Vec<(AccountId, U128)>,
+        keeper_id: AccountId,
+        keeper_bounty: Balance,
+    ) {
+        self.distributions.push(&Distribution {
+            staking_pool_account_id,
+            source,
+            epoch_height: env::epoch_height(),
+            timestamp: env::block_timestamp(),
+            amount,
+            payouts,
+            keeper_id,
+            keeper_bounty,
+        });
+    }
+}
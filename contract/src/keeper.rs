@@ -0,0 +1,122 @@
+use crate::*;
+
+/// Hard limits on the keeper bounty, so it can't be used to divert the rewards from the receivers.
+const MAX_KEEPER_BOUNTY_BASIS_POINTS: u32 = 500;
+const MAX_KEEPER_BOUNTY_AMOUNT: Balance = 1_000_000_000_000_000_000_000_000;
+
+/// Reward for the account that triggers a successful distribution, taken from the distributed rewards.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum KeeperBounty {
+    Fixed {
+        #[serde(with = "u128_dec_format")]
+        amount: Balance,
+    },
+    Fraction {
+        fraction: RewardFeeFraction,
+        #[serde(with = "u128_dec_format")]
+        max_amount: Balance,
+    },
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingKeeperBounty {
+    pub keeper_bounty: KeeperBounty,
+    pub effective_epoch: EpochHeight,
+}
+
+impl KeeperBounty {
+    pub fn assert_valid(&self) {
+        match self {
+            KeeperBounty::Fixed { amount } => {
+                assert!(*amount > 0, "The keeper bounty must be positive");
+                assert!(*amount <= MAX_KEEPER_BOUNTY_AMOUNT, "The keeper bounty can't exceed {}", MAX_KEEPER_BOUNTY_AMOUNT);
+            }
+            KeeperBounty::Fraction { fraction, max_amount } => {
+                fraction.assert_valid();
+                assert!(
+                    u64::from(fraction.numerator) * u64::from(BASIS_POINTS_DENOMINATOR)
+                        <= u64::from(MAX_KEEPER_BOUNTY_BASIS_POINTS) * u64::from(fraction.denominator),
+                    "The keeper bounty can't exceed {} basis points",
+                    MAX_KEEPER_BOUNTY_BASIS_POINTS
+                );
+                assert!(*max_amount > 0, "The keeper bounty cap must be positive");
+                assert!(*max_amount <= MAX_KEEPER_BOUNTY_AMOUNT, "The keeper bounty cap can't exceed {}", MAX_KEEPER_BOUNTY_AMOUNT);
+            }
+        }
+    }
+
+    /// Bounty out of the given rewards, never more than the hard limits allow.
+    pub fn amount(&self, rewards: Balance) -> Balance {
+        let amount = match self {
+            KeeperBounty::Fixed { amount } => *amount,
+            KeeperBounty::Fraction { fraction, max_amount } => std::cmp::min(fraction.multiply(rewards), *max_amount),
+        };
+        let max_fraction = RewardFeeFraction {
+            numerator: MAX_KEEPER_BOUNTY_BASIS_POINTS,
+            denominator: BASIS_POINTS_DENOMINATOR,
+        };
+        std::cmp::min(std::cmp::min(amount, max_fraction.multiply(rewards)), MAX_KEEPER_BOUNTY_AMOUNT)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // owner method to reward the caller of `withdraw` for every successful distribution,
+    // a new bounty takes effect after the reward receivers delay like any receivers change, `None` turns it off right away
+    pub fn set_keeper_bounty(&mut self, keeper_bounty: Option<KeeperBounty>) {
+        self.assert_owner();
+
+        self.keeper_bounty = self.internal_current_keeper_bounty();
+        self.keeper_bounty_change = None;
+        match keeper_bounty {
+            Some(keeper_bounty) => {
+                keeper_bounty.assert_valid();
+                let effective_epoch = env::epoch_height() + self.internal_reward_receivers_delay();
+                Event::KeeperBountyProposed {
+                    keeper_bounty: keeper_bounty.clone(),
+                    effective_epoch,
+                }.emit();
+                self.keeper_bounty_change = Some(PendingKeeperBounty {
+                    keeper_bounty,
+                    effective_epoch,
+                });
+            }
+            None => self.keeper_bounty = None,
+        }
+    }
+
+    pub fn get_keeper_bounty(&self) -> Option<KeeperBounty> {
+        self.internal_current_keeper_bounty()
+    }
+
+    pub fn get_keeper_bounty_change(&self) -> Option<PendingKeeperBounty> {
+        self.keeper_bounty_change.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_current_keeper_bounty(&self) -> Option<KeeperBounty> {
+        match &self.keeper_bounty_change {
+            Some(change) if change.effective_epoch <= env::epoch_height() => Some(change.keeper_bounty.clone()),
+            _ => self.keeper_bounty.clone(),
+        }
+    }
+
+    pub(crate) fn internal_keeper_bounty(&self, rewards: Balance) -> Balance {
+        self.internal_current_keeper_bounty().map_or(0, |keeper_bounty| keeper_bounty.amount(rewards))
+    }
+
+    pub(crate) fn internal_pay_keeper_bounty(&mut self, staking_pool_account_id: &AccountId, keeper_id: &AccountId, amount: Balance) {
+        if amount > 0 {
+            self.internal_credit(keeper_id, amount);
+            Event::KeeperBountyPaid {
+                staking_pool_account_id: staking_pool_account_id.clone(),
+                account_id: keeper_id.clone(),
+                amount: U128(amount),
+            }.emit();
+        }
+    }
+}
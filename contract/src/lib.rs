@@ -1,23 +1,62 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::json_types::{U128};
+use near_sdk::collections::{LookupMap, UnorderedMap, Vector};
+use near_sdk::json_types::{U128, U64};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    env, ext_contract, is_promise_success, log, near_bindgen, require, AccountId,
-    Balance, Gas, PanicOnDefault, Promise, Timestamp, EpochHeight
+    env, ext_contract, is_promise_success, log, near_bindgen, AccountId,
+    Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseError, PublicKey, Timestamp, EpochHeight
 };
 
+pub use crate::donations::*;
+pub use crate::events::*;
+pub use crate::fraction::*;
+pub use crate::gas::*;
+pub use crate::history::*;
+pub use crate::keeper::*;
+pub use crate::migrate::*;
+pub use crate::owner::*;
+pub use crate::pool_owner::*;
+pub use crate::pools::*;
+pub use crate::preview::*;
+pub use crate::receivers::*;
+pub use crate::roles::*;
+pub use crate::stats::*;
+
+mod carry_over;
+mod claims;
+mod donations;
+mod events;
+mod fraction;
+mod gas;
+mod history;
+mod keeper;
+mod migrate;
+mod owner;
+mod pool_owner;
+mod pools;
+mod preview;
+mod principal;
+mod receivers;
+mod roles;
+mod stats;
 mod web4;
 
-const STAKING_POOL_PING_GAS: Gas = Gas(50_000_000_000_000);
-const STAKING_POOL_READ_GAS: Gas = Gas(5_000_000_000_000);
-const ON_DISTRIBUTE_GAS: Gas = Gas(120_000_000_000_000);
-const WITHDRAW_GAS: Gas = Gas(25_000_000_000_000);
-const ON_WITHDRAW_GAS: Gas = Gas(60_000_000_000_000);
-const UNSTAKE_ALL_GAS: Gas = Gas(50_000_000_000_000);
-const NUM_EPOCHS_TO_UNLOCK: EpochHeight = 4;
+const MAX_PREPAID_GAS: Gas = Gas(300_000_000_000_000);
+const ON_CLAIM_GAS: Gas = Gas(10_000_000_000_000);
+const STAKING_POOL_OWNER_CALL_GAS: Gas = Gas(25_000_000_000_000);
+const STAKING_POOL_VOTE_GAS: Gas = Gas(125_000_000_000_000);
+const ON_STAKING_POOL_OWNER_CALL_GAS: Gas = Gas(10_000_000_000_000);
+const STAKING_POOL_DEPOSIT_GAS: Gas = Gas(50_000_000_000_000);
+const ON_STAKE_CHANGE_GAS: Gas = Gas(10_000_000_000_000);
+const MIGRATE_GAS: Gas = Gas(50_000_000_000_000);
+const DEFAULT_UNSTAKE_DELAY: EpochHeight = 4;
+const DEFAULT_STORAGE_RESERVE: Balance = 5_000_000_000_000_000_000_000_000;
+const MAX_REWARD_RECEIVERS: usize = 10;
+const DEFAULT_REWARD_RECEIVERS_DELAY: EpochHeight = 4;
+const OWNER_PROPOSAL_DURATION: Timestamp = 7 * 24 * 60 * 60 * 1_000_000_000;
 
 /// Represents an account structure readable by humans.
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct StakingPoolAccount {
     pub account_id: AccountId,
@@ -36,69 +75,110 @@ pub trait StakingPoolContract {
     fn ping(&mut self);
     /* Unstakes all staked balance */
     fn unstake_all(&mut self);
+    /* Unstakes the given amount of the staked balance */
+    fn unstake(&mut self, amount: U128);
+    /* Deposits the attached amount and stakes it */
+    fn deposit_and_stake(&mut self);
     /* Returns the unstaked balance of the given account */
     fn get_account(&self, account_id: AccountId);
     /* Withdraws the non staked balance for given account */
     fn withdraw(&mut self, amount: U128);
+    /* Owner method to update the staking key of the validator */
+    fn update_staking_key(&mut self, stake_public_key: PublicKey);
+    /* Owner method to update the reward fee fraction of the pool */
+    fn update_reward_fee_fraction(&mut self, reward_fee_fraction: RewardFeeFraction);
+    /* Owner method to vote through the pool on the given voting contract */
+    fn vote(&mut self, voting_account_id: AccountId, is_vote: bool);
+    /* Owner method to pause pool staking */
+    fn pause_staking(&mut self);
+    /* Owner method to resume pool staking */
+    fn resume_staking(&mut self);
 }
 
 #[ext_contract(ext_self)]
 pub trait ExtContract {
     /* Callback from checking unstaked balance */
-    fn on_get_account(&mut self, #[callback] account: StakingPoolAccount);
+    fn on_get_account(&mut self, staking_pool_account_id: AccountId, keeper_id: AccountId, #[callback_result] account: Result<StakingPoolAccount, PromiseError>);
     /* Callback from staking rewards withdraw */
-    fn on_withdraw(&mut self, unstaked_amount: U128, unstake_all: bool);
+    fn on_withdraw(&mut self, staking_pool_account_id: AccountId, keeper_id: AccountId, unstaked_amount: U128, staked_balance: U128);
+    /* Callback from unstaking after a distribution */
+    fn on_unstake(&mut self, staking_pool_account_id: AccountId, amount: U128, retained_stake: U128, unstake_all: bool);
+    /* Callback from reward claim transfer */
+    fn on_claim(&mut self, account_id: AccountId, amount: U128);
+    /* Callback from principal deposit and stake */
+    fn on_deposit_and_stake(&mut self, staking_pool_account_id: AccountId, account_id: AccountId, amount: U128);
+    /* Callback from principal unstake */
+    fn on_unstake_principal(&mut self, staking_pool_account_id: AccountId, amount: U128);
+    /* Callback from retained stake release */
+    fn on_release_retained_stake(&mut self, staking_pool_account_id: AccountId, amount: U128);
+    /* Callback from staking pool owner method call */
+    fn on_staking_pool_owner_call(&mut self, staking_pool_account_id: AccountId, method_name: String);
+}
+
+#[derive(BorshStorageKey, BorshSerialize)]
+pub(crate) enum StorageKey {
+    StakingPools,
+    PendingRewards,
+    RoleHolders,
+    Distributions,
+    ReceiverStats,
+    RewardReceivers { staking_pool_account_id: AccountId },
+    CarryOver,
+    Donations,
 }
 
 #[near_bindgen]
-#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault, Serialize)]
-#[serde(crate = "near_sdk::serde")]
+#[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
-    staking_pool_account_id: AccountId,
     owner_id: AccountId,
-    reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
-    next_distribution_epoch: EpochHeight,
-    #[serde(with = "u128_dec_format")]
-    rewards_received: Balance,
-    #[serde(with = "u64_dec_format")]
-    last_reward_distribution: Timestamp,
+    owner_proposal: Option<OwnerProposal>,
+    role_holders: LookupMap<Role, Vec<AccountId>>,
+    staking_pools: UnorderedMap<AccountId, StakingPool>,
+    reward_receivers_delay: EpochHeight,
+    reward_receivers_delay_change: Option<PendingRewardReceiversDelay>,
+    pending_rewards: LookupMap<AccountId, Balance>,
+    total_pending_rewards: Balance,
+    distributions: Vector<Distribution>,
+    receiver_stats: LookupMap<AccountId, ReceiverStats>,
+    gas_config: GasConfig,
+    keeper_bounty: Option<KeeperBounty>,
+    keeper_bounty_change: Option<PendingKeeperBounty>,
+    /// Payouts below it are carried over to a later distribution.
+    min_payout: Balance,
+    carry_over: LookupMap<AccountId, Balance>,
+    total_carry_over: Balance,
+    donations: Vector<Donation>,
+    /// Claims and principal deposits whose outcome is not known yet, they may still be refunded.
+    transfers_in_flight: Balance,
+    /// Balance kept on top of the storage cost when distributing the free balance.
+    storage_reserve: Balance,
+    /// Epochs to wait after an unstake before the balance is withdrawn, unless the pool sets its own.
+    unstake_delay: EpochHeight,
 
     web4_ipfs_hash: Option<String>,
 }
 
-#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone)]
+#[derive(Serialize)]
 #[serde(crate = "near_sdk::serde")]
-pub struct RewardFeeFraction {
-    pub numerator: u32,
-    pub denominator: u32,
-}
-
-impl RewardFeeFraction {
-    pub fn assert_valid(&self) {
-        assert_ne!(self.denominator, 0, "Denominator must be a positive number");
-        assert!(
-            self.numerator <= self.denominator,
-            "The reward fee must be less or equal to 1"
-        );
-    }
-
-    pub fn zero_fee() -> RewardFeeFraction {
-        RewardFeeFraction {
-            numerator: 0,
-            denominator: 1
-        }
-    }
-
-    pub fn multiply(&self, value: Balance) -> Balance {
-        (U256::from(self.numerator) * U256::from(value) / U256::from(self.denominator)).as_u128()
-    }
-
-    pub fn add(&self, other: RewardFeeFraction) -> RewardFeeFraction {
-        RewardFeeFraction {
-            numerator: (self.numerator * other.denominator + other.numerator * self.denominator),
-            denominator: (self.denominator * other.denominator)
-        }
-    }
+pub struct ContractInfo {
+    pub owner_id: AccountId,
+    pub owner_proposal: Option<OwnerProposal>,
+    pub staking_pools: Vec<(AccountId, StakingPoolView)>,
+    pub reward_receivers_delay: EpochHeight,
+    pub reward_receivers_delay_change: Option<PendingRewardReceiversDelay>,
+    #[serde(with = "u128_dec_format")]
+    pub total_pending_rewards: Balance,
+    pub gas_config: GasConfig,
+    pub keeper_bounty: Option<KeeperBounty>,
+    pub keeper_bounty_change: Option<PendingKeeperBounty>,
+    #[serde(with = "u128_dec_format")]
+    pub min_payout: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub total_carry_over: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub storage_reserve: Balance,
+    pub unstake_delay: EpochHeight,
+    pub web4_ipfs_hash: Option<String>,
 }
 
 #[near_bindgen]
@@ -107,106 +187,158 @@ impl Contract {
     pub fn new(
         staking_pool_account_id: AccountId,
         owner_id: AccountId,
-        reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+        reward_receivers: Vec<(AccountId, RewardShare)>,
     ) -> Self {
-
-        assert_reward_receivers(&reward_receivers);
-
-        Self {
-            staking_pool_account_id,
-            owner_id,
-            reward_receivers,
-            next_distribution_epoch: env::epoch_height(),
-            rewards_received: 0,
-            last_reward_distribution: 0,
-            web4_ipfs_hash: None,
-        }
+        let mut contract = Self::internal_new(owner_id, None);
+        contract.internal_add_staking_pool(staking_pool_account_id, parse_reward_receivers(reward_receivers));
+        write_state_version();
+        contract
     }
 
-    // owner method to update reward_receivers
-    pub fn reset_reward_receivers(&mut self, reward_receivers: Vec<(AccountId, RewardFeeFraction)>) {
-        self.assert_owner();
-        assert_reward_receivers(&reward_receivers);
-
-        self.reward_receivers = reward_receivers;
+    pub fn reset_next_distribution_epoch(&mut self, staking_pool_account_id: AccountId) {
+        self.assert_role(Role::Operator);
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.next_distribution_epoch = env::epoch_height();
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
     }
 
-    pub fn reset_next_distribution_epoch(&mut self) {
-        self.assert_owner();
-        self.next_distribution_epoch = env::epoch_height();
+    // public method to distribute rewards of the given staking pool, or of as many pools that are due
+    // as the attached gas covers, the caller earns the keeper bounty of every successful distribution
+    pub fn withdraw(&mut self, staking_pool_account_id: Option<AccountId>) -> Promise {
+        let mut available_gas = env::prepaid_gas() - env::used_gas();
+        let staking_pool_account_ids: Vec<AccountId> = if let Some(staking_pool_account_id) = staking_pool_account_id {
+            let staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+            assert!(!staking_pool.distribution_locked, "A distribution is already in progress");
+            assert!(staking_pool.is_active(), "The staking pool is not active until epoch {}", staking_pool.activation_epoch);
+            assert!(staking_pool.next_distribution_epoch <= env::epoch_height(), "The unstaked balance is not yet available due to unstaking delay");
+            let required_gas = self.internal_withdraw_gas(&staking_pool);
+            assert!(available_gas >= required_gas, "Not enough gas attached, {} is required", required_gas.0);
+            vec![staking_pool_account_id]
+        } else {
+            let mut skipped = vec![];
+            let staking_pool_account_ids: Vec<AccountId> = self.staking_pools
+                .iter()
+                .filter(|(_, staking_pool)| staking_pool.is_due())
+                .filter_map(|(staking_pool_account_id, staking_pool)| {
+                    let required_gas = self.internal_withdraw_gas(&staking_pool);
+                    if available_gas >= required_gas {
+                        available_gas -= required_gas;
+                        Some(staking_pool_account_id)
+                    } else {
+                        skipped.push(staking_pool_account_id.to_string());
+                        None
+                    }
+                })
+                .collect();
+            if !skipped.is_empty() {
+                assert!(!staking_pool_account_ids.is_empty(), "Not enough gas attached to distribute {}", skipped.join(", "));
+                log!("Not enough gas attached to distribute {} yet", skipped.join(", "));
+            }
+            staking_pool_account_ids
+        };
+
+        staking_pool_account_ids
+            .into_iter()
+            .map(|staking_pool_account_id| self.internal_withdraw(staking_pool_account_id, env::predecessor_account_id()))
+            .reduce(|promise, next| promise.and(next))
+            .expect("No staking pools are ready for distribution")
     }
 
-    // public method to distribute rewards
-    pub fn withdraw(&mut self) -> Promise {
-        assert!(self.next_distribution_epoch <= env::epoch_height(), "The unstaked balance is not yet available due to unstaking delay");
+    // the schedule only moves forward once an unstake or a withdraw is issued,
+    // a failed get_account is recorded and the distribution may be retried right away.
+    // The distribution lock is released here unless a withdraw or an unstake is issued, then their callbacks release it
+    #[private]
+    pub fn on_get_account(&mut self, staking_pool_account_id: AccountId, keeper_id: AccountId, #[callback_result] account: Result<StakingPoolAccount, PromiseError>) {
+        let account = match account {
+            Ok(account) => account,
+            Err(_) => {
+                self.internal_set_distribution_lock(&staking_pool_account_id, false);
+                let reason = "Failed to get the staking pool account".to_string();
+                self.internal_record_failure(&staking_pool_account_id, reason.clone());
+                Event::DistributionFailed {
+                    staking_pool_account_id,
+                    reason,
+                }.emit();
+                return;
+            }
+        };
+
+        if account.unstaked_balance.0 > 0 && account.can_withdraw {
+            let reward_receivers_count = self.internal_get_staking_pool(&staking_pool_account_id).reward_receivers.len();
+            ext_staking_pool::ext(staking_pool_account_id.clone())
+                .with_static_gas(self.gas_config.staking_pool_withdraw)
+                .withdraw(account.unstaked_balance)
+            .then(
+                ext_self::ext(env::current_account_id())
+                    .with_static_gas(self.gas_config.on_withdraw_gas(reward_receivers_count))
+                    .on_withdraw(staking_pool_account_id, keeper_id, account.unstaked_balance, account.staked_balance)
+            )
+            .as_return();
+            return;
+        }
 
-        ext_staking_pool::ext(self.staking_pool_account_id.clone())
-            .with_static_gas(STAKING_POOL_PING_GAS)
-            .ping()
-        .then(ext_staking_pool::ext(self.staking_pool_account_id.clone())
-            .with_static_gas(STAKING_POOL_READ_GAS)
-            .get_account(env::current_account_id())
-        )
-        .then(ext_self::ext(env::current_account_id())
-            .with_static_gas(ON_DISTRIBUTE_GAS)
-            .on_get_account()
-        )
+        if account.unstaked_balance.0 > 0 {
+            self.internal_set_distribution_lock(&staking_pool_account_id, false);
+            log!("Awaiting unstaking. Nothing to do. Can't withdraw yet");
+        } else {
+            self.internal_unstake(staking_pool_account_id, account.staked_balance.0);
+        }
     }
 
     #[private]
-    pub fn on_get_account(&mut self, #[callback] account: StakingPoolAccount) {
-        let unstake_all = account.staked_balance.0 > 0;
-        self.next_distribution_epoch = env::epoch_height() + NUM_EPOCHS_TO_UNLOCK;
-        if account.unstaked_balance.0 > 0 {
-            if account.can_withdraw {
-                log!(
-                    "Withdrawing from staking pool: {}",
-                    account.unstaked_balance.0
-                );
-                ext_staking_pool::ext(self.staking_pool_account_id.clone())
-                    .with_static_gas(WITHDRAW_GAS)
-                    .withdraw(account.unstaked_balance)
-                .then(
-                    ext_self::ext(env::current_account_id())
-                        .with_static_gas(ON_WITHDRAW_GAS)
-                        .on_withdraw(account.unstaked_balance, unstake_all)
-                )
-                .as_return();
-            } else {
-                log!("Awaiting unstaking. Nothing to do. Can't withdraw yet");
-            }
-        } else if unstake_all {
-            self.internal_unstake_all();
+    pub fn on_withdraw(&mut self, staking_pool_account_id: AccountId, keeper_id: AccountId, unstaked_amount: U128, staked_balance: U128) {
+        if !is_promise_success() {
+            // nothing was withdrawn or unstaked, so the distribution may be retried right away
+            let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+            staking_pool.next_distribution_epoch = env::epoch_height();
+            staking_pool.distribution_locked = false;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_record_failure(&staking_pool_account_id, "Failed to withdraw from the staking pool".to_string());
+            Event::WithdrawFailed {
+                staking_pool_account_id,
+                amount: unstaked_amount,
+            }.emit();
+            return;
         }
-    }
+        Event::WithdrawSucceeded {
+            staking_pool_account_id: staking_pool_account_id.clone(),
+            amount: unstaked_amount,
+        }.emit();
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        let rewards = self.internal_take_principal(&mut staking_pool, unstaked_amount.0);
+        staking_pool.rewards_received += rewards;
+
+        if rewards > 0 {
+            let keeper_bounty = self.internal_keeper_bounty(rewards);
+            self.internal_pay_keeper_bounty(&staking_pool_account_id, &keeper_id, keeper_bounty);
+            self.internal_distribute(&staking_pool_account_id, &mut staking_pool, DistributionSource::StakingPool, rewards, keeper_id, keeper_bounty);
+            staking_pool.last_reward_distribution = env::block_timestamp();
+        }
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
 
-    fn internal_unstake_all(&mut self) {
-        log!("Unstaking all from staking pool");
-        ext_staking_pool::ext(self.staking_pool_account_id.clone())
-            .with_static_gas(UNSTAKE_ALL_GAS)
-            .unstake_all()
-        .as_return();
+        self.internal_unstake(staking_pool_account_id, staked_balance.0);
     }
 
+    // the retained stake and the schedule are only committed once the unstake succeeded,
+    // otherwise the distribution may be retried right away
     #[private]
-    pub fn on_withdraw(&mut self, unstaked_amount: U128, unstake_all: bool) {
-        require!(is_promise_success(), "Withdraw failed");
-        log!(
-            "Withdraw success! Received unstaked rewards: {}",
-            unstaked_amount.0
-        );
-        self.rewards_received += unstaked_amount.0;
-
-        if unstaked_amount.0 > 0 {
-            // Send rewards
-            for reward_receiver in &self.reward_receivers {
-                transfer(reward_receiver.0.clone(), reward_receiver.1.multiply(unstaked_amount.0));
+    pub fn on_unstake(&mut self, staking_pool_account_id: AccountId, amount: U128, retained_stake: U128, unstake_all: bool) {
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.distribution_locked = false;
+        if is_promise_success() {
+            staking_pool.retained_stake = retained_stake.0;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_schedule_next_distribution(&staking_pool_account_id, true);
+            if unstake_all {
+                Event::UnstakeAll { staking_pool_account_id }.emit();
+            } else {
+                Event::Unstake { staking_pool_account_id, amount }.emit();
             }
-            self.last_reward_distribution = env::block_timestamp();
-        }
-
-        if unstake_all {
-            self.internal_unstake_all();
+        } else {
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_schedule_next_distribution(&staking_pool_account_id, false);
+            self.internal_record_failure(&staking_pool_account_id, "Failed to unstake from the staking pool".to_string());
+            Event::UnstakeFailed { staking_pool_account_id, amount }.emit();
         }
     }
 
@@ -216,20 +348,157 @@ impl Contract {
         (now, eh)
     }
 
-    pub fn get_staking_pool(&self) -> AccountId {
-        self.staking_pool_account_id.clone()
-    }
-
-    pub fn get_info(&self) -> &Self {
-        self
+    pub fn get_info(&self) -> ContractInfo {
+        ContractInfo {
+            owner_id: self.owner_id.clone(),
+            owner_proposal: self.owner_proposal.clone(),
+            staking_pools: self.staking_pools
+                .iter()
+                .map(|(staking_pool_account_id, staking_pool)| (staking_pool_account_id, staking_pool.into()))
+                .collect(),
+            reward_receivers_delay: self.internal_reward_receivers_delay(),
+            reward_receivers_delay_change: self.reward_receivers_delay_change.clone(),
+            total_pending_rewards: self.total_pending_rewards,
+            gas_config: self.gas_config.clone(),
+            keeper_bounty: self.internal_current_keeper_bounty(),
+            keeper_bounty_change: self.keeper_bounty_change.clone(),
+            min_payout: self.min_payout,
+            total_carry_over: self.total_carry_over,
+            storage_reserve: self.storage_reserve,
+            unstake_delay: self.unstake_delay,
+            web4_ipfs_hash: self.web4_ipfs_hash.clone(),
+        }
     }
 
-    pub fn get_rewards_received(&self) -> Balance {
-        self.rewards_received
+    // rewards received by the given staking pool, or by all staking pools together
+    pub fn get_rewards_received(&self, staking_pool_account_id: Option<AccountId>) -> Balance {
+        if let Some(staking_pool_account_id) = staking_pool_account_id {
+            self.internal_get_staking_pool(&staking_pool_account_id).rewards_received
+        } else {
+            self.staking_pools.values().map(|staking_pool| staking_pool.rewards_received).sum()
+        }
     }
 }
 
 impl Contract {
+    pub(crate) fn internal_new(owner_id: AccountId, web4_ipfs_hash: Option<String>) -> Self {
+        Self {
+            owner_id,
+            owner_proposal: None,
+            role_holders: LookupMap::new(StorageKey::RoleHolders),
+            staking_pools: UnorderedMap::new(StorageKey::StakingPools),
+            reward_receivers_delay: DEFAULT_REWARD_RECEIVERS_DELAY,
+            reward_receivers_delay_change: None,
+            pending_rewards: LookupMap::new(StorageKey::PendingRewards),
+            total_pending_rewards: 0,
+            distributions: Vector::new(StorageKey::Distributions),
+            receiver_stats: LookupMap::new(StorageKey::ReceiverStats),
+            gas_config: GasConfig::default(),
+            keeper_bounty: None,
+            keeper_bounty_change: None,
+            min_payout: 0,
+            carry_over: LookupMap::new(StorageKey::CarryOver),
+            total_carry_over: 0,
+            donations: Vector::new(StorageKey::Donations),
+            transfers_in_flight: 0,
+            storage_reserve: DEFAULT_STORAGE_RESERVE,
+            unstake_delay: DEFAULT_UNSTAKE_DELAY,
+            web4_ipfs_hash,
+        }
+    }
+
+    // splits the amount left after the keeper bounty among the reward receivers of the staking pool,
+    // credits the payouts and records the distribution
+    pub(crate) fn internal_distribute(
+        &mut self,
+        staking_pool_account_id: &AccountId,
+        staking_pool: &mut StakingPool,
+        source: DistributionSource,
+        amount: Balance,
+        keeper_id: AccountId,
+        keeper_bounty: Balance,
+    ) {
+        // Credit rewards, receivers claim them with `claim`
+        let (payouts, dust) = split_rewards(
+            &staking_pool.reward_receivers.to_vec(),
+            staking_pool.remainder_receiver.as_ref(),
+            amount - keeper_bounty,
+        );
+        staking_pool.dust_allocated += dust;
+        let payouts: Vec<(AccountId, Balance)> = payouts
+            .into_iter()
+            .map(|(account_id, amount)| {
+                let amount = self.internal_carry_over(staking_pool_account_id, &account_id, amount);
+                (account_id, amount)
+            })
+            .collect();
+        for (account_id, amount) in &payouts {
+            self.internal_credit(account_id, *amount);
+            self.internal_update_receiver_stats(account_id, *amount);
+            if *amount > 0 {
+                Event::RewardPayout {
+                    staking_pool_account_id: staking_pool_account_id.clone(),
+                    account_id: account_id.clone(),
+                    amount: U128(*amount),
+                }.emit();
+            }
+        }
+        let payouts = payouts.into_iter().map(|(account_id, amount)| (account_id, U128(amount))).collect();
+        self.internal_record_distribution(Some(staking_pool_account_id.clone()), source, amount, payouts, keeper_id, keeper_bounty);
+    }
+
+    fn internal_withdraw(&mut self, staking_pool_account_id: AccountId, keeper_id: AccountId) -> Promise {
+        self.internal_apply_pending_reward_receivers(&staking_pool_account_id);
+        self.internal_set_distribution_lock(&staking_pool_account_id, true);
+        Event::WithdrawStarted {
+            staking_pool_account_id: staking_pool_account_id.clone(),
+        }.emit();
+
+        let reward_receivers_count = self.internal_get_staking_pool(&staking_pool_account_id).reward_receivers.len();
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(self.gas_config.staking_pool_ping)
+            .ping()
+        .then(ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(self.gas_config.staking_pool_get_account)
+            .get_account(env::current_account_id())
+        )
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(self.gas_config.on_get_account_gas(reward_receivers_count))
+            .on_get_account(staking_pool_account_id, keeper_id)
+        )
+    }
+
+    // unstakes the staked balance except for the principal, the retained stake and the compounded share
+    // of the new rewards. The distribution lock is held until on_unstake commits the retained stake
+    // and schedules the next distribution, without an unstake both happen right away
+    fn internal_unstake(&mut self, staking_pool_account_id: AccountId, staked_balance: Balance) {
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        let (unstake_amount, retained_stake) = staking_pool.unstake_plan(staked_balance);
+        if unstake_amount == 0 {
+            staking_pool.retained_stake = retained_stake;
+            staking_pool.distribution_locked = false;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_schedule_next_distribution(&staking_pool_account_id, false);
+            return;
+        }
+        staking_pool.distribution_locked = true;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+
+        let unstake_all = retained_stake == 0 && staking_pool.principal == 0;
+        let staking_pool_contract = ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(self.gas_config.staking_pool_unstake);
+        if unstake_all {
+            staking_pool_contract.unstake_all()
+        } else {
+            staking_pool_contract.unstake(U128(unstake_amount))
+        }
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(ON_STAKE_CHANGE_GAS)
+            .on_unstake(staking_pool_account_id, U128(unstake_amount), U128(retained_stake), unstake_all)
+        )
+        .as_return();
+    }
+
     pub fn assert_owner(&self) {
         assert_eq!(
             &self.owner_id,
@@ -239,21 +508,50 @@ impl Contract {
     }
 }
 
-fn assert_reward_receivers (reward_receivers: &Vec<(AccountId, RewardFeeFraction)>){
-    let mut total_fee: RewardFeeFraction = RewardFeeFraction::zero_fee();
-    for reward_receiver in reward_receivers {
-        reward_receiver.1.assert_valid();
-        total_fee = total_fee.add(reward_receiver.1.clone());
+fn assert_reward_receivers (staking_pool_account_id: &AccountId, reward_receivers: &[(AccountId, RewardFeeFraction)]){
+    assert!(!reward_receivers.is_empty(), "ERR_NO_REWARD_RECEIVERS");
+    // set_gas_config makes sure the withdraw chain of this many receivers fits into the prepaid gas
+    assert!(
+        reward_receivers.len() <= MAX_REWARD_RECEIVERS,
+        "ERR_TOO_MANY_REWARD_RECEIVERS: at most {} are allowed",
+        MAX_REWARD_RECEIVERS
+    );
+
+    let mut total_fee = Ratio::zero();
+    for (index, reward_receiver) in reward_receivers.iter().enumerate() {
+        assert!(
+            !reward_receivers[..index].iter().any(|(account_id, _)| account_id == &reward_receiver.0),
+            "ERR_DUPLICATE_REWARD_RECEIVER: {}",
+            reward_receiver.0
+        );
+        assert!(
+            reward_receiver.0 != env::current_account_id() && &reward_receiver.0 != staking_pool_account_id,
+            "ERR_ILLEGAL_REWARD_RECEIVER: {} can't receive rewards",
+            reward_receiver.0
+        );
+        assert!(reward_receiver.1.numerator > 0, "ERR_ZERO_REWARD_FEE: {}", reward_receiver.0);
+        total_fee = reward_receiver.1
+            .checked_ratio()
+            .and_then(|fee| total_fee.checked_add(fee))
+            .unwrap_or_else(|err| env::panic_str(&format!("Invalid reward fee of {}: {}", reward_receiver.0, err)));
     }
-    total_fee.assert_valid();
-    assert_eq!(total_fee.numerator, total_fee.denominator, "ERR_ILLEGAL_REWARD_RECEIVERS");
+    assert!(total_fee.is_one(), "ERR_ILLEGAL_REWARD_RECEIVERS: shares sum up to {} instead of 1", total_fee);
 }
 
-fn transfer(account: AccountId, amount: Balance) {
-    if amount > 0 {
-        log!("Sending {} to {}", amount, account);
-        Promise::new(account).transfer(amount);
-    }
+fn parse_reward_share(account_id: &AccountId, share: &RewardShare) -> RewardFeeFraction {
+    share
+        .to_fee_fraction()
+        .unwrap_or_else(|err| env::panic_str(&format!("Invalid reward share of {}: {}", account_id, err)))
+}
+
+fn parse_reward_receivers(reward_receivers: Vec<(AccountId, RewardShare)>) -> Vec<(AccountId, RewardFeeFraction)> {
+    reward_receivers
+        .into_iter()
+        .map(|(account_id, share)| {
+            let fraction = parse_reward_share(&account_id, &share);
+            (account_id, fraction)
+        })
+        .collect()
 }
 
 uint::construct_uint!(
@@ -0,0 +1,123 @@
+use crate::*;
+
+const STATE_KEY: &[u8] = b"STATE";
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+const STATE_VERSION: u32 = 2;
+
+/// Single staking pool layout, before `web4_ipfs_hash` was added.
+#[derive(BorshDeserialize)]
+pub struct ContractV0 {
+    staking_pool_account_id: AccountId,
+    owner_id: AccountId,
+    reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    next_distribution_epoch: EpochHeight,
+    rewards_received: Balance,
+    last_reward_distribution: Timestamp,
+}
+
+/// Single staking pool layout with `web4_ipfs_hash`.
+#[derive(BorshDeserialize)]
+pub struct ContractV1 {
+    staking_pool_account_id: AccountId,
+    owner_id: AccountId,
+    reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    next_distribution_epoch: EpochHeight,
+    rewards_received: Balance,
+    last_reward_distribution: Timestamp,
+    web4_ipfs_hash: Option<String>,
+}
+
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(ContractV1),
+    V2(Box<Contract>),
+}
+
+impl VersionedContract {
+    /// Reads the stored state. Layouts older than V2 carry no version tag,
+    /// so they are told apart by which one consumes the whole state.
+    pub fn read() -> Self {
+        let state = env::storage_read(STATE_KEY).expect("ERR_NO_STATE");
+        let version = env::storage_read(STATE_VERSION_KEY)
+            .map(|version| u32::try_from_slice(&version).expect("ERR_STATE_VERSION"));
+
+        match version {
+            Some(2) => VersionedContract::V2(Box::new(Contract::try_from_slice(&state).expect("ERR_STATE_V2"))),
+            Some(version) => env::panic_str(&format!("Unknown state version {}", version)),
+            None => ContractV1::try_from_slice(&state)
+                .map(VersionedContract::V1)
+                .or_else(|_| ContractV0::try_from_slice(&state).map(VersionedContract::V0))
+                .expect("ERR_UNKNOWN_STATE"),
+        }
+    }
+
+    pub fn into_current(self) -> Contract {
+        match self {
+            VersionedContract::V0(state) => migrate_single_pool(
+                state.owner_id,
+                None,
+                state.staking_pool_account_id,
+                state.reward_receivers,
+                state.next_distribution_epoch,
+                state.rewards_received,
+                state.last_reward_distribution,
+            ),
+            VersionedContract::V1(state) => migrate_single_pool(
+                state.owner_id,
+                state.web4_ipfs_hash,
+                state.staking_pool_account_id,
+                state.reward_receivers,
+                state.next_distribution_epoch,
+                state.rewards_received,
+                state.last_reward_distribution,
+            ),
+            VersionedContract::V2(state) => *state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let contract = VersionedContract::read().into_current();
+        write_state_version();
+        contract
+    }
+
+    // owner method to deploy the new contract code passed as the raw input and migrate the state
+    pub fn upgrade(&self) -> Promise {
+        self.assert_owner();
+        let code = env::input().expect("ERR_NO_CODE");
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), vec![], 0, MIGRATE_GAS)
+    }
+
+    pub fn get_state_version(&self) -> u32 {
+        STATE_VERSION
+    }
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+fn migrate_single_pool(
+    owner_id: AccountId,
+    web4_ipfs_hash: Option<String>,
+    staking_pool_account_id: AccountId,
+    reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    next_distribution_epoch: EpochHeight,
+    rewards_received: Balance,
+    last_reward_distribution: Timestamp,
+) -> Contract {
+    let mut contract = Contract::internal_new(owner_id, web4_ipfs_hash);
+    let mut staking_pool = StakingPool::new(&staking_pool_account_id, &reward_receivers);
+    staking_pool.next_distribution_epoch = next_distribution_epoch;
+    staking_pool.rewards_received = rewards_received;
+    staking_pool.last_reward_distribution = last_reward_distribution;
+    contract.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+    contract
+}
@@ -0,0 +1,60 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnerProposal {
+    pub owner_id: AccountId,
+    #[serde(with = "u64_dec_format")]
+    pub expires_at: Timestamp,
+}
+
+#[near_bindgen]
+impl Contract {
+    // owner method to start the ownership transfer, the new owner has to accept it before it expires
+    pub fn propose_owner(&mut self, owner_id: AccountId) {
+        self.assert_owner();
+        assert_ne!(owner_id, self.owner_id, "Already an owner");
+
+        let expires_at = env::block_timestamp() + OWNER_PROPOSAL_DURATION;
+        Event::OwnerProposed {
+            owner_id: owner_id.clone(),
+            expires_at: U64(expires_at),
+        }.emit();
+        self.owner_proposal = Some(OwnerProposal {
+            owner_id,
+            expires_at,
+        });
+    }
+
+    pub fn cancel_owner_proposal(&mut self) {
+        self.assert_owner();
+        let owner_proposal = self.owner_proposal.take().expect("No owner proposal");
+        Event::OwnerProposalCancelled {
+            owner_id: owner_proposal.owner_id,
+        }.emit();
+    }
+
+    pub fn accept_owner(&mut self) {
+        let owner_proposal = self.owner_proposal.take().expect("No owner proposal");
+        assert_eq!(
+            &owner_proposal.owner_id,
+            &env::predecessor_account_id(),
+            "Not a proposed owner!"
+        );
+        assert!(owner_proposal.expires_at >= env::block_timestamp(), "Owner proposal expired");
+
+        Event::OwnerChanged {
+            old_owner_id: self.owner_id.clone(),
+            new_owner_id: owner_proposal.owner_id.clone(),
+        }.emit();
+        self.owner_id = owner_proposal.owner_id;
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    pub fn get_owner_proposal(&self) -> Option<OwnerProposal> {
+        self.owner_proposal.clone()
+    }
+}
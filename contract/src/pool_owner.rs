@@ -0,0 +1,95 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingPoolOwnerCall {
+    pub method_name: String,
+    pub success: bool,
+    #[serde(with = "u64_dec_format")]
+    pub timestamp: Timestamp,
+}
+
+#[near_bindgen]
+impl Contract {
+    // operator method to rotate the validator key of the given staking pool
+    pub fn update_staking_key(&mut self, staking_pool_account_id: AccountId, stake_public_key: PublicKey) -> Promise {
+        self.assert_staking_pool_operator(&staking_pool_account_id);
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(STAKING_POOL_OWNER_CALL_GAS)
+            .update_staking_key(stake_public_key)
+        .then(staking_pool_owner_call_callback(staking_pool_account_id, "update_staking_key"))
+    }
+
+    // owner method, the pool fee decides how much income is left for the reward receivers
+    pub fn update_reward_fee_fraction(&mut self, staking_pool_account_id: AccountId, reward_fee_fraction: RewardFeeFraction) -> Promise {
+        self.assert_staking_pool_owner(&staking_pool_account_id);
+        reward_fee_fraction.assert_valid();
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(STAKING_POOL_OWNER_CALL_GAS)
+            .update_reward_fee_fraction(reward_fee_fraction)
+        .then(staking_pool_owner_call_callback(staking_pool_account_id, "update_reward_fee_fraction"))
+    }
+
+    // owner method to vote on behalf of the validator
+    pub fn vote(&mut self, staking_pool_account_id: AccountId, voting_account_id: AccountId, is_vote: bool) -> Promise {
+        self.assert_staking_pool_owner(&staking_pool_account_id);
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(STAKING_POOL_VOTE_GAS)
+            .vote(voting_account_id, is_vote)
+        .then(staking_pool_owner_call_callback(staking_pool_account_id, "vote"))
+    }
+
+    pub fn pause_staking(&mut self, staking_pool_account_id: AccountId) -> Promise {
+        self.assert_staking_pool_operator(&staking_pool_account_id);
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(STAKING_POOL_OWNER_CALL_GAS)
+            .pause_staking()
+        .then(staking_pool_owner_call_callback(staking_pool_account_id, "pause_staking"))
+    }
+
+    pub fn resume_staking(&mut self, staking_pool_account_id: AccountId) -> Promise {
+        self.assert_staking_pool_operator(&staking_pool_account_id);
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(STAKING_POOL_OWNER_CALL_GAS)
+            .resume_staking()
+        .then(staking_pool_owner_call_callback(staking_pool_account_id, "resume_staking"))
+    }
+
+    #[private]
+    pub fn on_staking_pool_owner_call(&mut self, staking_pool_account_id: AccountId, method_name: String) -> bool {
+        let success = is_promise_success();
+        Event::StakingPoolOwnerCall {
+            staking_pool_account_id: staking_pool_account_id.clone(),
+            method_name: method_name.clone(),
+            success,
+        }.emit();
+
+        if let Some(mut staking_pool) = self.staking_pools.get(&staking_pool_account_id) {
+            staking_pool.last_owner_call = Some(StakingPoolOwnerCall {
+                method_name,
+                success,
+                timestamp: env::block_timestamp(),
+            });
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+        }
+        success
+    }
+}
+
+impl Contract {
+    fn assert_staking_pool_operator(&self, staking_pool_account_id: &AccountId) {
+        self.assert_role(Role::Operator);
+        assert!(self.staking_pools.get(staking_pool_account_id).is_some(), "ERR_NO_STAKING_POOL");
+    }
+
+    fn assert_staking_pool_owner(&self, staking_pool_account_id: &AccountId) {
+        self.assert_owner();
+        assert!(self.staking_pools.get(staking_pool_account_id).is_some(), "ERR_NO_STAKING_POOL");
+    }
+}
+
+fn staking_pool_owner_call_callback(staking_pool_account_id: AccountId, method_name: &str) -> Promise {
+    ext_self::ext(env::current_account_id())
+        .with_static_gas(ON_STAKING_POOL_OWNER_CALL_GAS)
+        .on_staking_pool_owner_call(staking_pool_account_id, method_name.to_string())
+}
@@ -0,0 +1,313 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct StakingPool {
+    pub reward_receivers: UnorderedMap<AccountId, RewardFeeFraction>,
+    pub pending_reward_receivers: Option<PendingRewardReceivers>,
+    /// Receives the rounding remainder of every distribution, defaults to the largest share.
+    pub remainder_receiver: Option<AccountId>,
+    /// Rounding remainder allocated on top of the receiver shares so far.
+    pub dust_allocated: Balance,
+    pub next_distribution_epoch: EpochHeight,
+    /// First epoch the receivers of a newly added pool may be paid, `reset_next_distribution_epoch` can't move it.
+    pub activation_epoch: EpochHeight,
+    /// Custom unstake delay of the pool, overrides the contract wide one.
+    pub unstake_delay: Option<EpochHeight>,
+    /// Share of the new rewards kept staked on every unstake.
+    pub compounding_fraction: RewardFeeFraction,
+    /// Balance kept staked by compounding so far.
+    pub retained_stake: Balance,
+    /// Owner stake deposited with `deposit_and_stake`, it is kept staked and never distributed.
+    pub principal: Balance,
+    /// Principal unstaked by `withdraw_principal`, credited to the owner on the next withdraw.
+    pub principal_unstaked: Balance,
+    /// Set while the withdraw promise chain of the pool is in flight.
+    pub distribution_locked: bool,
+    pub rewards_received: Balance,
+    pub last_reward_distribution: Timestamp,
+    pub last_owner_call: Option<StakingPoolOwnerCall>,
+    pub last_failure: Option<DistributionFailure>,
+}
+
+/// Staking pool as returned by views, reward receivers are listed with `get_reward_receivers`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakingPoolView {
+    pub reward_receivers_count: u64,
+    pub pending_reward_receivers: Option<PendingRewardReceivers>,
+    pub remainder_receiver: Option<AccountId>,
+    #[serde(with = "u128_dec_format")]
+    pub dust_allocated: Balance,
+    pub next_distribution_epoch: EpochHeight,
+    pub activation_epoch: EpochHeight,
+    pub unstake_delay: Option<EpochHeight>,
+    pub compounding_fraction: RewardFeeFraction,
+    #[serde(with = "u128_dec_format")]
+    pub retained_stake: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub principal: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub principal_unstaked: Balance,
+    pub distribution_locked: bool,
+    #[serde(with = "u128_dec_format")]
+    pub rewards_received: Balance,
+    #[serde(with = "u64_dec_format")]
+    pub last_reward_distribution: Timestamp,
+    pub last_owner_call: Option<StakingPoolOwnerCall>,
+    pub last_failure: Option<DistributionFailure>,
+}
+
+impl From<StakingPool> for StakingPoolView {
+    fn from(staking_pool: StakingPool) -> Self {
+        Self {
+            reward_receivers_count: staking_pool.reward_receivers.len(),
+            pending_reward_receivers: staking_pool.pending_reward_receivers,
+            remainder_receiver: staking_pool.remainder_receiver,
+            dust_allocated: staking_pool.dust_allocated,
+            next_distribution_epoch: staking_pool.next_distribution_epoch,
+            activation_epoch: staking_pool.activation_epoch,
+            unstake_delay: staking_pool.unstake_delay,
+            compounding_fraction: staking_pool.compounding_fraction,
+            retained_stake: staking_pool.retained_stake,
+            principal: staking_pool.principal,
+            principal_unstaked: staking_pool.principal_unstaked,
+            distribution_locked: staking_pool.distribution_locked,
+            rewards_received: staking_pool.rewards_received,
+            last_reward_distribution: staking_pool.last_reward_distribution,
+            last_owner_call: staking_pool.last_owner_call,
+            last_failure: staking_pool.last_failure,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DistributionFailure {
+    pub reason: String,
+    pub epoch_height: EpochHeight,
+    #[serde(with = "u64_dec_format")]
+    pub timestamp: Timestamp,
+}
+
+impl StakingPool {
+    pub fn new(staking_pool_account_id: &AccountId, reward_receivers: &[(AccountId, RewardFeeFraction)]) -> Self {
+        let mut staking_pool = Self {
+            reward_receivers: UnorderedMap::new(StorageKey::RewardReceivers {
+                staking_pool_account_id: staking_pool_account_id.clone(),
+            }),
+            pending_reward_receivers: None,
+            remainder_receiver: None,
+            dust_allocated: 0,
+            next_distribution_epoch: env::epoch_height(),
+            activation_epoch: env::epoch_height(),
+            unstake_delay: None,
+            compounding_fraction: RewardFeeFraction::zero_fee(),
+            retained_stake: 0,
+            principal: 0,
+            principal_unstaked: 0,
+            distribution_locked: false,
+            rewards_received: 0,
+            last_reward_distribution: 0,
+            last_owner_call: None,
+            last_failure: None,
+        };
+        staking_pool.set_reward_receivers(reward_receivers);
+        staking_pool
+    }
+
+    pub fn set_reward_receivers(&mut self, reward_receivers: &[(AccountId, RewardFeeFraction)]) {
+        self.reward_receivers.clear();
+        for (account_id, fraction) in reward_receivers {
+            self.reward_receivers.insert(account_id, fraction);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.activation_epoch <= env::epoch_height()
+    }
+
+    /// Whether `withdraw` accepts the staking pool now.
+    pub fn is_due(&self) -> bool {
+        !self.distribution_locked && self.is_active() && self.next_distribution_epoch <= env::epoch_height()
+    }
+
+    /// Reward receivers of the next distribution, the pending ones once they are effective.
+    pub fn effective_reward_receivers(&self) -> Vec<(AccountId, RewardFeeFraction)> {
+        match &self.pending_reward_receivers {
+            Some(pending) if pending.effective_epoch <= env::epoch_height() => pending.reward_receivers.clone(),
+            _ => self.reward_receivers.to_vec(),
+        }
+    }
+
+    /// Part of the withdrawn amount that is unstaked principal.
+    pub fn principal_share(&self, amount: Balance) -> Balance {
+        std::cmp::min(self.principal_unstaked, amount)
+    }
+
+    /// Amount to unstake out of the staked balance and the stake retained afterwards.
+    /// The principal and the retained stake stay staked, as does the compounded share of the new rewards.
+    pub fn unstake_plan(&self, staked_balance: Balance) -> (Balance, Balance) {
+        let staked_rewards = staked_balance.saturating_sub(self.principal);
+        let retained_stake = std::cmp::min(self.retained_stake, staked_rewards);
+        let rewards = staked_rewards - retained_stake;
+        let compounded = self.compounding_fraction.multiply(rewards);
+        (rewards - compounded, retained_stake + compounded)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    // owner method to register one more staking pool owned by this contract,
+    // its first distribution waits for the reward receivers delay like any other receivers change
+    pub fn add_staking_pool(&mut self, staking_pool_account_id: AccountId, reward_receivers: Vec<(AccountId, RewardShare)>) {
+        self.assert_owner();
+        self.internal_add_staking_pool(staking_pool_account_id.clone(), parse_reward_receivers(reward_receivers));
+
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.activation_epoch = env::epoch_height() + self.internal_reward_receivers_delay();
+        staking_pool.next_distribution_epoch = staking_pool.activation_epoch;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+    }
+
+    pub fn remove_staking_pool(&mut self, staking_pool_account_id: AccountId) {
+        self.assert_owner();
+        let mut staking_pool = self.staking_pools.remove(&staking_pool_account_id).expect("ERR_NO_STAKING_POOL");
+        assert!(!staking_pool.distribution_locked, "A distribution is in progress");
+        assert!(staking_pool.principal == 0 && staking_pool.principal_unstaked == 0, "The staking pool still holds principal");
+        assert!(staking_pool.retained_stake == 0, "The staking pool still holds retained stake, release it first");
+        staking_pool.reward_receivers.clear();
+        Event::StakingPoolRemoved { staking_pool_account_id }.emit();
+    }
+
+    // owner method to release the distribution lock if the withdraw promise chain got stuck
+    pub fn unlock_distribution(&mut self, staking_pool_account_id: AccountId) {
+        self.assert_owner();
+        assert!(self.internal_get_staking_pool(&staking_pool_account_id).distribution_locked, "Distribution is not locked");
+        self.internal_set_distribution_lock(&staking_pool_account_id, false);
+        Event::DistributionUnlocked { staking_pool_account_id }.emit();
+    }
+
+    // owner method to set the number of epochs to wait after an unstake before withdrawing
+    pub fn set_unstake_delay(&mut self, unstake_delay: EpochHeight) {
+        self.assert_owner();
+        self.unstake_delay = unstake_delay;
+    }
+
+    // owner method to give the staking pool its own unstake delay, `None` falls back to the contract wide one
+    pub fn set_staking_pool_unstake_delay(&mut self, staking_pool_account_id: AccountId, unstake_delay: Option<EpochHeight>) {
+        self.assert_owner();
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.unstake_delay = unstake_delay;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+    }
+
+    // owner method to keep the given share of the new rewards staked on every unstake,
+    // lowering it later doesn't release the stake retained so far, see `release_retained_stake`
+    pub fn set_compounding_fraction(&mut self, staking_pool_account_id: AccountId, compounding_fraction: RewardShare) {
+        self.assert_owner();
+        let compounding_fraction = parse_reward_share(&staking_pool_account_id, &compounding_fraction);
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.compounding_fraction = compounding_fraction;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+    }
+
+    // owner method to unstake the given part of the retained stake, the next withdraw distributes it as rewards.
+    // The distribution lock is held until the unstake outcome is known
+    pub fn release_retained_stake(&mut self, staking_pool_account_id: AccountId, amount: U128) -> Promise {
+        self.assert_owner();
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        assert!(!staking_pool.distribution_locked, "A distribution is in progress");
+        assert!(amount.0 > 0 && amount.0 <= staking_pool.retained_stake, "Not enough retained stake");
+        staking_pool.retained_stake -= amount.0;
+        staking_pool.distribution_locked = true;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(self.gas_config.staking_pool_unstake)
+            .unstake(amount)
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(ON_STAKE_CHANGE_GAS)
+            .on_release_retained_stake(staking_pool_account_id, amount)
+        )
+    }
+
+    #[private]
+    pub fn on_release_retained_stake(&mut self, staking_pool_account_id: AccountId, amount: U128) {
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.distribution_locked = false;
+        if is_promise_success() {
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_schedule_next_distribution(&staking_pool_account_id, true);
+            Event::RetainedStakeReleased { staking_pool_account_id, amount }.emit();
+        } else {
+            staking_pool.retained_stake += amount.0;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            Event::RetainedStakeReleaseFailed { staking_pool_account_id, amount }.emit();
+        }
+    }
+
+    // unstake delay of the given staking pool, or the contract wide one
+    pub fn get_unstake_delay(&self, staking_pool_account_id: Option<AccountId>) -> EpochHeight {
+        staking_pool_account_id
+            .map_or(self.unstake_delay, |staking_pool_account_id| {
+                self.internal_unstake_delay(&self.internal_get_staking_pool(&staking_pool_account_id))
+            })
+    }
+
+    pub fn is_distribution_locked(&self, staking_pool_account_id: AccountId) -> bool {
+        self.internal_get_staking_pool(&staking_pool_account_id).distribution_locked
+    }
+
+    pub fn get_staking_pools(&self) -> Vec<AccountId> {
+        self.staking_pools.keys().collect()
+    }
+
+    pub fn get_staking_pool(&self, staking_pool_account_id: AccountId) -> Option<StakingPoolView> {
+        self.staking_pools.get(&staking_pool_account_id).map(StakingPoolView::from)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_add_staking_pool(&mut self, staking_pool_account_id: AccountId, reward_receivers: Vec<(AccountId, RewardFeeFraction)>) {
+        assert_reward_receivers(&staking_pool_account_id, &reward_receivers);
+        assert!(self.staking_pools.get(&staking_pool_account_id).is_none(), "ERR_STAKING_POOL_EXISTS");
+
+        self.staking_pools.insert(&staking_pool_account_id, &StakingPool::new(&staking_pool_account_id, &reward_receivers));
+        Event::StakingPoolAdded { staking_pool_account_id }.emit();
+    }
+
+    pub(crate) fn internal_set_distribution_lock(&mut self, staking_pool_account_id: &AccountId, distribution_locked: bool) {
+        let mut staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        staking_pool.distribution_locked = distribution_locked;
+        self.staking_pools.insert(staking_pool_account_id, &staking_pool);
+    }
+
+    // after an unstake the next distribution waits for the unstake delay, otherwise it may be retried right away
+    pub(crate) fn internal_schedule_next_distribution(&mut self, staking_pool_account_id: &AccountId, unstaked: bool) {
+        let mut staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        staking_pool.next_distribution_epoch = if unstaked {
+            env::epoch_height() + self.internal_unstake_delay(&staking_pool)
+        } else {
+            env::epoch_height()
+        };
+        self.staking_pools.insert(staking_pool_account_id, &staking_pool);
+    }
+
+    pub(crate) fn internal_unstake_delay(&self, staking_pool: &StakingPool) -> EpochHeight {
+        staking_pool.unstake_delay.unwrap_or(self.unstake_delay)
+    }
+
+    pub(crate) fn internal_record_failure(&mut self, staking_pool_account_id: &AccountId, reason: String) {
+        let mut staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        staking_pool.last_failure = Some(DistributionFailure {
+            reason,
+            epoch_height: env::epoch_height(),
+            timestamp: env::block_timestamp(),
+        });
+        self.staking_pools.insert(staking_pool_account_id, &staking_pool);
+    }
+
+    pub(crate) fn internal_get_staking_pool(&self, staking_pool_account_id: &AccountId) -> StakingPool {
+        self.staking_pools.get(staking_pool_account_id).expect("ERR_NO_STAKING_POOL")
+    }
+}
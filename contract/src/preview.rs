@@ -0,0 +1,115 @@
+use crate::*;
+
+/// What `on_get_account` does with the staking pool account.
+#[derive(Serialize, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum DistributionAction {
+    /// Withdraw the unstaked balance, distribute it and unstake again.
+    Withdraw,
+    /// Nothing to withdraw yet, unstake the rewards.
+    Unstake,
+    /// The unstaked balance is not yet available.
+    Wait,
+    /// Nothing to withdraw or unstake.
+    Idle,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DistributionPreview {
+    pub action: DistributionAction,
+    pub epoch_height: EpochHeight,
+    pub next_distribution_epoch: EpochHeight,
+    pub distribution_locked: bool,
+    /// Whether `withdraw` accepts the staking pool now.
+    pub is_due: bool,
+    pub withdraw_amount: U128,
+    pub principal: U128,
+    pub rewards: U128,
+    pub keeper_bounty: U128,
+    /// Amounts credited to the receivers, carried over balances included.
+    pub payouts: Vec<(AccountId, U128)>,
+    /// Balances carried over to a later distribution as they are below the minimum payout.
+    pub carried_over: Vec<(AccountId, U128)>,
+    pub dust: U128,
+    pub unstake_amount: U128,
+    /// Epoch the following distribution is scheduled for.
+    pub scheduled_epoch: EpochHeight,
+}
+
+#[near_bindgen]
+impl Contract {
+    // dry run of the distribution for the given snapshot of the contract account in the staking pool,
+    // as returned by its `get_account`
+    pub fn preview_distribution(&self, staking_pool_account_id: AccountId, account: StakingPoolAccount) -> DistributionPreview {
+        let staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        let epoch_height = env::epoch_height();
+
+        let mut withdraw_amount = 0;
+        let mut principal = 0;
+        let mut rewards = 0;
+        let mut keeper_bounty = 0;
+        let mut payouts = vec![];
+        let mut carried_over = vec![];
+        let mut dust = 0;
+        let mut unstake_amount = 0;
+        let action = if account.unstaked_balance.0 > 0 && account.can_withdraw {
+            withdraw_amount = account.unstaked_balance.0;
+            principal = staking_pool.principal_share(withdraw_amount);
+            rewards = withdraw_amount - principal;
+            if rewards > 0 {
+                keeper_bounty = self.internal_keeper_bounty(rewards);
+                let split = split_rewards(
+                    &staking_pool.effective_reward_receivers(),
+                    staking_pool.remainder_receiver.as_ref(),
+                    rewards - keeper_bounty,
+                );
+                for (account_id, amount) in split.0 {
+                    let (payout, carry_over) = self.internal_batch_payout(&account_id, amount);
+                    if carry_over > 0 {
+                        carried_over.push((account_id.clone(), U128(carry_over)));
+                    }
+                    payouts.push((account_id, U128(payout)));
+                }
+                dust = split.1;
+            }
+            unstake_amount = staking_pool.unstake_plan(account.staked_balance.0).0;
+            DistributionAction::Withdraw
+        } else if account.unstaked_balance.0 > 0 {
+            DistributionAction::Wait
+        } else {
+            unstake_amount = staking_pool.unstake_plan(account.staked_balance.0).0;
+            if unstake_amount > 0 {
+                DistributionAction::Unstake
+            } else {
+                DistributionAction::Idle
+            }
+        };
+
+        let scheduled_epoch = if action == DistributionAction::Wait {
+            staking_pool.next_distribution_epoch
+        } else if unstake_amount > 0 {
+            epoch_height + self.internal_unstake_delay(&staking_pool)
+        } else {
+            epoch_height
+        };
+
+        DistributionPreview {
+            action,
+            epoch_height,
+            next_distribution_epoch: staking_pool.next_distribution_epoch,
+            distribution_locked: staking_pool.distribution_locked,
+            is_due: staking_pool.is_due(),
+            withdraw_amount: U128(withdraw_amount),
+            principal: U128(principal),
+            rewards: U128(rewards),
+            keeper_bounty: U128(keeper_bounty),
+            payouts,
+            carried_over,
+            dust: U128(dust),
+            unstake_amount: U128(unstake_amount),
+            scheduled_epoch,
+        }
+    }
+}
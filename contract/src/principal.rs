@@ -0,0 +1,97 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    // owner method to stake its own balance in the given staking pool, it is tracked as principal and never distributed.
+    // The distribution lock is held until the deposit is accounted for
+    #[payable]
+    pub fn deposit_and_stake(&mut self, staking_pool_account_id: AccountId) -> Promise {
+        self.assert_owner();
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach the balance to stake");
+        assert!(!self.internal_get_staking_pool(&staking_pool_account_id).distribution_locked, "A distribution is in progress");
+        self.internal_set_distribution_lock(&staking_pool_account_id, true);
+        self.transfers_in_flight += amount;
+
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_attached_deposit(amount)
+            .with_static_gas(STAKING_POOL_DEPOSIT_GAS)
+            .deposit_and_stake()
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(ON_STAKE_CHANGE_GAS)
+            .on_deposit_and_stake(staking_pool_account_id, env::predecessor_account_id(), U128(amount))
+        )
+    }
+
+    // owner method to unstake the given principal, the next withdraw of the staking pool
+    // credits it to the owner instead of the reward receivers.
+    // The distribution lock is held until the unstake outcome is known
+    pub fn withdraw_principal(&mut self, staking_pool_account_id: AccountId, amount: U128) -> Promise {
+        self.assert_owner();
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        assert!(!staking_pool.distribution_locked, "A distribution is in progress");
+        assert!(amount.0 > 0 && amount.0 <= staking_pool.principal, "Not enough principal");
+        staking_pool.principal -= amount.0;
+        staking_pool.principal_unstaked += amount.0;
+        staking_pool.distribution_locked = true;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+
+        ext_staking_pool::ext(staking_pool_account_id.clone())
+            .with_static_gas(self.gas_config.staking_pool_unstake)
+            .unstake(amount)
+        .then(ext_self::ext(env::current_account_id())
+            .with_static_gas(ON_STAKE_CHANGE_GAS)
+            .on_unstake_principal(staking_pool_account_id, amount)
+        )
+    }
+
+    #[private]
+    pub fn on_unstake_principal(&mut self, staking_pool_account_id: AccountId, amount: U128) {
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.distribution_locked = false;
+        if is_promise_success() {
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            self.internal_schedule_next_distribution(&staking_pool_account_id, true);
+            Event::PrincipalUnstaked { staking_pool_account_id, amount }.emit();
+        } else {
+            staking_pool.principal_unstaked -= amount.0;
+            staking_pool.principal += amount.0;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            Event::PrincipalUnstakeFailed { staking_pool_account_id, amount }.emit();
+        }
+    }
+
+    #[private]
+    pub fn on_deposit_and_stake(&mut self, staking_pool_account_id: AccountId, account_id: AccountId, amount: U128) {
+        self.transfers_in_flight -= amount.0;
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        staking_pool.distribution_locked = false;
+        if is_promise_success() {
+            staking_pool.principal += amount.0;
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            Event::PrincipalDeposited { staking_pool_account_id, amount }.emit();
+        } else {
+            self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+            Promise::new(account_id).transfer(amount.0);
+            Event::PrincipalDepositFailed { staking_pool_account_id, amount }.emit();
+        }
+    }
+
+    pub fn get_principal(&self, staking_pool_account_id: AccountId) -> U128 {
+        U128(self.internal_get_staking_pool(&staking_pool_account_id).principal)
+    }
+}
+
+impl Contract {
+    // takes the unstaked principal out of the withdrawn amount and credits it to the owner,
+    // returns the rewards left for distribution
+    pub(crate) fn internal_take_principal(&mut self, staking_pool: &mut StakingPool, amount: Balance) -> Balance {
+        let principal = staking_pool.principal_share(amount);
+        if principal > 0 {
+            staking_pool.principal_unstaked -= principal;
+            let owner_id = self.owner_id.clone();
+            self.internal_credit(&owner_id, principal);
+        }
+        amount - principal
+    }
+}
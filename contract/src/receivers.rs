@@ -0,0 +1,323 @@
+use crate::*;
+
+use std::convert::TryFrom;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingRewardReceivers {
+    pub reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    pub effective_epoch: EpochHeight,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingRewardReceiversDelay {
+    pub reward_receivers_delay: EpochHeight,
+    pub effective_epoch: EpochHeight,
+}
+
+#[near_bindgen]
+impl Contract {
+    // owner method to queue new reward_receivers of the given staking pool,
+    // they replace the current ones on the first withdraw after the reward receivers delay
+    pub fn reset_reward_receivers(&mut self, staking_pool_account_id: AccountId, reward_receivers: Vec<(AccountId, RewardShare)>) {
+        self.assert_owner();
+        self.internal_propose_reward_receivers(&staking_pool_account_id, parse_reward_receivers(reward_receivers));
+    }
+
+    // owner method to queue adding a receiver, the other shares shrink proportionally to make room for it
+    pub fn add_receiver(&mut self, staking_pool_account_id: AccountId, account_id: AccountId, share: RewardShare) {
+        self.assert_owner();
+        let fraction = parse_reward_share(&account_id, &share);
+
+        let reward_receivers = self.internal_next_reward_receivers(&staking_pool_account_id);
+        assert!(
+            !reward_receivers.iter().any(|(receiver_id, _)| receiver_id == &account_id),
+            "ERR_DUPLICATE_REWARD_RECEIVER: {}",
+            account_id
+        );
+        let mut reward_receivers = rebalance(&staking_pool_account_id, reward_receivers, Some(&fraction));
+        reward_receivers.push((account_id, fraction));
+        self.internal_propose_reward_receivers(&staking_pool_account_id, reward_receivers);
+    }
+
+    // owner method to queue removing a receiver, its share is spread proportionally over the others
+    pub fn remove_receiver(&mut self, staking_pool_account_id: AccountId, account_id: AccountId) {
+        self.assert_owner();
+
+        let mut reward_receivers = self.internal_next_reward_receivers(&staking_pool_account_id);
+        let index = reward_receiver_index(&reward_receivers, &account_id);
+        reward_receivers.remove(index);
+        let reward_receivers = rebalance(&staking_pool_account_id, reward_receivers, None);
+        self.internal_propose_reward_receivers(&staking_pool_account_id, reward_receivers);
+    }
+
+    // owner method to queue a new share of a receiver, the other shares are rescaled proportionally
+    pub fn update_receiver_share(&mut self, staking_pool_account_id: AccountId, account_id: AccountId, share: RewardShare) {
+        self.assert_owner();
+        let fraction = parse_reward_share(&account_id, &share);
+
+        let mut reward_receivers = self.internal_next_reward_receivers(&staking_pool_account_id);
+        let index = reward_receiver_index(&reward_receivers, &account_id);
+        reward_receivers.remove(index);
+        let mut reward_receivers = rebalance(&staking_pool_account_id, reward_receivers, Some(&fraction));
+        reward_receivers.insert(index, (account_id, fraction));
+        self.internal_propose_reward_receivers(&staking_pool_account_id, reward_receivers);
+    }
+
+    pub fn cancel_reward_receivers_change(&mut self, staking_pool_account_id: AccountId) {
+        self.assert_owner();
+
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        assert!(staking_pool.pending_reward_receivers.take().is_some(), "No pending reward receivers");
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+        Event::RewardReceiversChangeCancelled { staking_pool_account_id }.emit();
+    }
+
+    // owner method to update the reward receivers delay,
+    // a shorter delay only applies once the current delay has passed
+    pub fn set_reward_receivers_delay(&mut self, reward_receivers_delay: EpochHeight) {
+        self.assert_owner();
+
+        let current_delay = self.internal_reward_receivers_delay();
+        self.reward_receivers_delay = current_delay;
+        if reward_receivers_delay >= current_delay {
+            self.reward_receivers_delay = reward_receivers_delay;
+            self.reward_receivers_delay_change = None;
+        } else {
+            self.reward_receivers_delay_change = Some(PendingRewardReceiversDelay {
+                reward_receivers_delay,
+                effective_epoch: env::epoch_height() + current_delay,
+            });
+        }
+    }
+
+    // owner method to choose the receiver of the rounding remainder, `None` picks the largest share
+    pub fn set_remainder_receiver(&mut self, staking_pool_account_id: AccountId, remainder_receiver: Option<AccountId>) {
+        self.assert_owner();
+
+        let mut staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        if let Some(remainder_receiver) = remainder_receiver.as_ref() {
+            let is_pending_reward_receiver = staking_pool.pending_reward_receivers
+                .iter()
+                .flat_map(|pending| pending.reward_receivers.iter())
+                .any(|(account_id, _)| account_id == remainder_receiver);
+            assert!(
+                staking_pool.reward_receivers.get(remainder_receiver).is_some() || is_pending_reward_receiver,
+                "The remainder receiver must be one of the reward receivers"
+            );
+        }
+        staking_pool.remainder_receiver = remainder_receiver;
+        self.staking_pools.insert(&staking_pool_account_id, &staking_pool);
+    }
+
+    // rounding remainder allocated by the given staking pool, or by all staking pools together
+    pub fn get_dust_allocated(&self, staking_pool_account_id: Option<AccountId>) -> U128 {
+        U128(if let Some(staking_pool_account_id) = staking_pool_account_id {
+            self.internal_get_staking_pool(&staking_pool_account_id).dust_allocated
+        } else {
+            self.staking_pools.values().map(|staking_pool| staking_pool.dust_allocated).sum()
+        })
+    }
+
+    pub fn get_reward_receivers(&self, staking_pool_account_id: AccountId, from_index: Option<u64>, limit: Option<u64>) -> Vec<(AccountId, RewardFeeFraction)> {
+        let staking_pool = self.internal_get_staking_pool(&staking_pool_account_id);
+        let keys = staking_pool.reward_receivers.keys_as_vector();
+        let values = staking_pool.reward_receivers.values_as_vector();
+        let from_index = from_index.unwrap_or(0);
+        let limit = limit.unwrap_or(MAX_REWARD_RECEIVERS as u64);
+        (from_index..std::cmp::min(from_index.saturating_add(limit), keys.len()))
+            .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap()))
+            .collect()
+    }
+
+    pub fn get_reward_receivers_delay(&self) -> EpochHeight {
+        self.internal_reward_receivers_delay()
+    }
+
+    pub fn get_pending_reward_receivers(&self, staking_pool_account_id: AccountId) -> Option<PendingRewardReceivers> {
+        self.internal_get_staking_pool(&staking_pool_account_id).pending_reward_receivers
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_reward_receivers_delay(&self) -> EpochHeight {
+        match &self.reward_receivers_delay_change {
+            Some(change) if change.effective_epoch <= env::epoch_height() => change.reward_receivers_delay,
+            _ => self.reward_receivers_delay,
+        }
+    }
+
+    pub(crate) fn internal_propose_reward_receivers(&mut self, staking_pool_account_id: &AccountId, reward_receivers: Vec<(AccountId, RewardFeeFraction)>) {
+        assert_reward_receivers(staking_pool_account_id, &reward_receivers);
+
+        let mut staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        let effective_epoch = env::epoch_height() + self.internal_reward_receivers_delay();
+        Event::RewardReceiversProposed {
+            staking_pool_account_id: staking_pool_account_id.clone(),
+            reward_receivers: reward_receivers.clone(),
+            effective_epoch,
+        }.emit();
+        staking_pool.pending_reward_receivers = Some(PendingRewardReceivers {
+            reward_receivers,
+            effective_epoch,
+        });
+        self.staking_pools.insert(staking_pool_account_id, &staking_pool);
+    }
+
+    // incremental edits build on the pending reward receivers, if any, so they compose
+    fn internal_next_reward_receivers(&self, staking_pool_account_id: &AccountId) -> Vec<(AccountId, RewardFeeFraction)> {
+        let staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        match staking_pool.pending_reward_receivers {
+            Some(pending) => pending.reward_receivers,
+            None => staking_pool.reward_receivers.to_vec(),
+        }
+    }
+
+    pub(crate) fn internal_apply_pending_reward_receivers(&mut self, staking_pool_account_id: &AccountId) {
+        let mut staking_pool = self.internal_get_staking_pool(staking_pool_account_id);
+        match staking_pool.pending_reward_receivers.take() {
+            Some(pending) if pending.effective_epoch <= env::epoch_height() => {
+                Event::RewardReceiversChanged {
+                    staking_pool_account_id: staking_pool_account_id.clone(),
+                    reward_receivers: pending.reward_receivers.clone(),
+                }.emit();
+                staking_pool.set_reward_receivers(&pending.reward_receivers);
+                self.staking_pools.insert(staking_pool_account_id, &staking_pool);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn reward_receiver_index(reward_receivers: &[(AccountId, RewardFeeFraction)], account_id: &AccountId) -> usize {
+    reward_receivers
+        .iter()
+        .position(|(receiver_id, _)| receiver_id == account_id)
+        .unwrap_or_else(|| env::panic_str(&format!("ERR_NO_REWARD_RECEIVER: {}", account_id)))
+}
+
+/// Rescales the shares proportionally so they leave exactly `reserved` for another receiver,
+/// or sum up to one when nothing is reserved.
+fn rebalance(
+    staking_pool_account_id: &AccountId,
+    reward_receivers: Vec<(AccountId, RewardFeeFraction)>,
+    reserved: Option<&RewardFeeFraction>,
+) -> Vec<(AccountId, RewardFeeFraction)> {
+    if reward_receivers.is_empty() {
+        return reward_receivers;
+    }
+    let rebalanced = || -> Result<Vec<(AccountId, RewardFeeFraction)>, FractionError> {
+        let target = match reserved {
+            Some(reserved) => Ratio::one().checked_sub(reserved.checked_ratio()?)?,
+            None => Ratio::one(),
+        };
+        let total = reward_receivers
+            .iter()
+            .try_fold(Ratio::zero(), |total, (_, fraction)| total.checked_add(fraction.checked_ratio()?))?;
+        let factor = target.checked_div(total)?;
+        reward_receivers
+            .iter()
+            .map(|(account_id, fraction)| {
+                let share = fraction.checked_ratio()?.checked_mul(factor)?;
+                Ok((account_id.clone(), RewardFeeFraction::try_from(share)?))
+            })
+            .collect()
+    };
+    rebalanced().unwrap_or_else(|err| env::panic_str(&format!(
+        "Can't rebalance the reward receivers of {}: {}, use reset_reward_receivers instead",
+        staking_pool_account_id,
+        err
+    )))
+}
+
+/// Splits `amount` by the receiver shares. The rounding remainder goes to the remainder receiver,
+/// or to the receiver with the largest share (the first one on ties), so the payouts sum up to `amount`.
+/// Returns the payouts and the allocated remainder.
+pub(crate) fn split_rewards(
+    reward_receivers: &[(AccountId, RewardFeeFraction)],
+    remainder_receiver: Option<&AccountId>,
+    amount: Balance,
+) -> (Vec<(AccountId, Balance)>, Balance) {
+    let mut payouts: Vec<(AccountId, Balance)> = reward_receivers
+        .iter()
+        .map(|(account_id, fraction)| (account_id.clone(), fraction.multiply(amount)))
+        .collect();
+    let dust = amount - payouts.iter().map(|(_, amount)| amount).sum::<Balance>();
+
+    let remainder_index = remainder_receiver
+        .and_then(|remainder_receiver| reward_receivers.iter().position(|(account_id, _)| account_id == remainder_receiver))
+        .or_else(|| largest_share_index(reward_receivers));
+    if let Some(index) = remainder_index {
+        payouts[index].1 += dust;
+        (payouts, dust)
+    } else {
+        (payouts, 0)
+    }
+}
+
+fn largest_share_index(reward_receivers: &[(AccountId, RewardFeeFraction)]) -> Option<usize> {
+    (0..reward_receivers.len()).fold(None, |largest, index| match largest {
+        Some(largest) if !is_larger_share(&reward_receivers[index].1, &reward_receivers[largest].1) => Some(largest),
+        _ => Some(index),
+    })
+}
+
+fn is_larger_share(fraction: &RewardFeeFraction, other: &RewardFeeFraction) -> bool {
+    u64::from(fraction.numerator) * u64::from(other.denominator)
+        > u64::from(other.numerator) * u64::from(fraction.denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(account_id: &str) -> AccountId {
+        account_id.parse().unwrap()
+    }
+
+    fn receivers(shares: &[(&str, u32, u32)]) -> Vec<(AccountId, RewardFeeFraction)> {
+        shares
+            .iter()
+            .map(|(account_id, numerator, denominator)| {
+                (account(account_id), RewardFeeFraction { numerator: *numerator, denominator: *denominator })
+            })
+            .collect()
+    }
+
+    fn total(payouts: &[(AccountId, Balance)]) -> Balance {
+        payouts.iter().map(|(_, amount)| amount).sum()
+    }
+
+    #[test]
+    fn remainder_goes_to_the_remainder_receiver() {
+        let reward_receivers = receivers(&[("a.near", 1, 3), ("b.near", 1, 3), ("c.near", 1, 3)]);
+        let (payouts, dust) = split_rewards(&reward_receivers, Some(&account("c.near")), 100);
+        assert_eq!(dust, 1);
+        assert_eq!(payouts, vec![(account("a.near"), 33), (account("b.near"), 33), (account("c.near"), 34)]);
+    }
+
+    #[test]
+    fn remainder_defaults_to_the_first_largest_share() {
+        let reward_receivers = receivers(&[("a.near", 1, 7), ("b.near", 3, 7), ("c.near", 3, 7)]);
+        let (payouts, dust) = split_rewards(&reward_receivers, None, 10);
+        assert_eq!(dust, 1);
+        assert_eq!(payouts, vec![(account("a.near"), 1), (account("b.near"), 5), (account("c.near"), 4)]);
+
+        // a remainder receiver that is no longer a receiver falls back to the largest share
+        let (payouts, _) = split_rewards(&reward_receivers, Some(&account("gone.near")), 10);
+        assert_eq!(payouts[1], (account("b.near"), 5));
+    }
+
+    #[test]
+    fn payouts_sum_up_to_the_amount() {
+        let reward_receivers = receivers(&[("a.near", 199, 1000), ("b.near", 201, 1000), ("c.near", 1, 5), ("d.near", 3, 20), ("e.near", 1, 4)]);
+        for amount in [0, 1, 7, 999, 1_000_003, 123_456_789_012_345_678_901_234_567, u128::MAX / 2] {
+            for remainder_receiver in [None, Some(account("a.near")), Some(account("e.near"))] {
+                let (payouts, dust) = split_rewards(&reward_receivers, remainder_receiver.as_ref(), amount);
+                assert_eq!(total(&payouts), amount);
+                assert!(dust < reward_receivers.len() as Balance);
+            }
+        }
+    }
+}
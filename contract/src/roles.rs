@@ -0,0 +1,72 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    /* Manages roles, staking pools and reward receivers */
+    Owner,
+    /* Operates the distribution schedule and the staking pools */
+    Operator,
+    /* Pushes pending rewards to the reward receivers */
+    Keeper,
+    /* Manages the web4 frontend */
+    WebAdmin,
+}
+
+const ROLES: [Role; 4] = [Role::Owner, Role::Operator, Role::Keeper, Role::WebAdmin];
+
+#[near_bindgen]
+impl Contract {
+    pub fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+        assert_ne!(role, Role::Owner, "Use propose_owner to transfer the Owner role");
+
+        let mut role_holders = self.role_holders.get(&role).unwrap_or_default();
+        assert!(!role_holders.contains(&account_id), "Role already granted");
+        role_holders.push(account_id.clone());
+        self.role_holders.insert(&role, &role_holders);
+        Event::RoleGranted { role, account_id }.emit();
+    }
+
+    pub fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner();
+        assert_ne!(role, Role::Owner, "Use propose_owner to transfer the Owner role");
+
+        let mut role_holders = self.role_holders.get(&role).unwrap_or_default();
+        let index = role_holders.iter().position(|holder| holder == &account_id).expect("Role not granted");
+        role_holders.swap_remove(index);
+        self.role_holders.insert(&role, &role_holders);
+        Event::RoleRevoked { role, account_id }.emit();
+    }
+
+    pub fn get_role_holders(&self, role: Role) -> Vec<AccountId> {
+        if role == Role::Owner {
+            vec![self.owner_id.clone()]
+        } else {
+            self.role_holders.get(&role).unwrap_or_default()
+        }
+    }
+
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        ROLES.iter()
+            .filter(|role| self.has_role(**role, &account_id))
+            .copied()
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn has_role(&self, role: Role, account_id: &AccountId) -> bool {
+        // the owner holds every role
+        &self.owner_id == account_id
+            || self.role_holders.get(&role).unwrap_or_default().contains(account_id)
+    }
+
+    pub fn assert_role(&self, role: Role) {
+        assert!(
+            self.has_role(role, &env::predecessor_account_id()),
+            "Not a {:?}!",
+            role
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use crate::*;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReceiverStats {
+    #[serde(with = "u128_dec_format")]
+    pub total_received: Balance,
+    pub payouts_count: u64,
+    #[serde(with = "u64_dec_format")]
+    pub first_payout_at: Timestamp,
+    #[serde(with = "u64_dec_format")]
+    pub last_payout_at: Timestamp,
+}
+
+#[near_bindgen]
+impl Contract {
+    // lifetime totals of the rewards distributed to the given receiver across all staking pools
+    pub fn get_receiver_stats(&self, account_id: AccountId) -> Option<ReceiverStats> {
+        self.receiver_stats.get(&account_id)
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_update_receiver_stats(&mut self, account_id: &AccountId, amount: Balance) {
+        if amount == 0 {
+            return;
+        }
+        let mut stats = self.receiver_stats.get(account_id).unwrap_or_default();
+        if stats.payouts_count == 0 {
+            stats.first_payout_at = env::block_timestamp();
+        }
+        stats.total_received += amount;
+        stats.payouts_count += 1;
+        stats.last_payout_at = env::block_timestamp();
+        self.receiver_stats.insert(account_id, &stats);
+    }
+}
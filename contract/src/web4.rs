@@ -70,8 +70,9 @@ impl Web4Response {
 #[near_bindgen]
 impl Contract {
     pub fn set_ipfs_hash(&mut self, ipfs_hash: Option<String>) {
-        self.assert_owner();
-        self.web4_ipfs_hash = ipfs_hash;
+        self.assert_role(Role::WebAdmin);
+        self.web4_ipfs_hash = ipfs_hash.clone();
+        Event::IpfsHashChanged { ipfs_hash }.emit();
     }
 
     pub fn get_ipfs_hash(&self) -> String {
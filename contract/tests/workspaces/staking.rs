@@ -151,6 +151,7 @@ async fn main() -> anyhow::Result<()> {
 
     let _outcome_withdraw = owner
         .call(&worker, contract.id(), "withdraw")
+        .args_json(json!({}))?
         .gas(parse_gas!("200 T") as u64)
         .transact()
         .await?;
@@ -159,6 +160,7 @@ async fn main() -> anyhow::Result<()> {
     /*
     let outcome_withdraw_failed: CallExecutionDetails = owner
         .call(&worker, contract.id(), "withdraw")
+        .args_json(json!({}))?
         .gas(parse_gas!("200 T") as u64)
         .transact()
         .await?;
@@ -201,17 +203,31 @@ async fn main() -> anyhow::Result<()> {
 
     let _outcome_withdraw = owner
         .call(&worker, contract.id(), "withdraw")
+        .args_json(json!({}))?
         .gas(parse_gas!("200 T") as u64)
         .transact()
         .await?;
     //println!("withdraw outcome: {:#?}", _outcome_withdraw);
 
     let rewards_received_1: Balance = contract.call(&worker, "get_rewards_received")
+        .args_json(json!({}))?
         .view()
         .await?
         .json::<Balance>()?;
     println!("rewards_received 1: {}", rewards_received_1);
 
+    for reward_account in [&reward_1, &reward_2] {
+        let _outcome_claim = owner
+            .call(&worker, contract.id(), "claim_for")
+            .args_json(json!({
+                    "account_id": reward_account.id(),
+            }))?
+            .gas(parse_gas!("50 T") as u64)
+            .transact()
+            .await?;
+        //println!("claim outcome: {:#?}", _outcome_claim);
+    }
+
     let init_balance_1_2 = reward_1.view_account(&worker).await?.balance;
     let init_balance_2_2 = reward_2.view_account(&worker).await?.balance;
 
@@ -254,6 +270,7 @@ async fn main() -> anyhow::Result<()> {
 
     let _outcome_withdraw = owner
         .call(&worker, contract.id(), "withdraw")
+        .args_json(json!({}))?
         .gas(parse_gas!("200 T") as u64)
         .transact()
         .await?;
@@ -282,6 +299,7 @@ async fn main() -> anyhow::Result<()> {
 
     let _outcome_withdraw = owner
         .call(&worker, contract.id(), "withdraw")
+        .args_json(json!({}))?
         .gas(parse_gas!("200 T") as u64)
         .transact()
         .await?;
@@ -289,11 +307,24 @@ async fn main() -> anyhow::Result<()> {
 
 
     let rewards_received_2: Balance = contract.call(&worker, "get_rewards_received")
+        .args_json(json!({}))?
         .view()
         .await?
         .json::<Balance>()?;
     println!("rewards_received 2: {}", rewards_received_2);
 
+    for reward_account in [&reward_1, &reward_2] {
+        let _outcome_claim = owner
+            .call(&worker, contract.id(), "claim_for")
+            .args_json(json!({
+                    "account_id": reward_account.id(),
+            }))?
+            .gas(parse_gas!("50 T") as u64)
+            .transact()
+            .await?;
+        //println!("claim outcome: {:#?}", _outcome_claim);
+    }
+
     let init_balance_1_3 = reward_1.view_account(&worker).await?.balance;
     let init_balance_2_3 = reward_2.view_account(&worker).await?.balance;
 